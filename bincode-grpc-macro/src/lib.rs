@@ -1,7 +1,7 @@
-use heck::{ShoutySnakeCase, SnakeCase};
+use heck::{CamelCase, ShoutySnakeCase, SnakeCase};
 use proc_macro::TokenStream;
 use quote::ToTokens;
-use syn::export::TokenStream2;
+use syn::export::{Span, TokenStream2};
 use syn::parse::{Parse, ParseStream};
 use syn::{token, FnArg, ImplItem};
 use syn::{Attribute, Ident, ReturnType, Visibility};
@@ -26,22 +26,174 @@ use syn::{Attribute, Ident, ReturnType, Visibility};
 /// }
 /// ```
 ///
-/// // generated create service function (`service_create_fn_ident`)
+/// // generated create service functions (`service_create_fn_ident`, `service_create_with_options_fn_ident`)
 /// ```
 /// pub fn create_greeter<S: Greeter + Send + Clone + 'static>(s: S) -> ::bincode_grpc::grpcio::Service {
+///     create_greeter_with_options(s, ::bincode_grpc::ServiceOptions::default())
+/// }
+///
+/// pub fn create_greeter_with_options<S: Greeter + Send + Clone + 'static>(s: S, options: ::bincode_grpc::ServiceOptions) -> ::bincode_grpc::grpcio::Service {
 ///     let mut builder = ::bincode_grpc::grpcio::ServiceBuilder::new();
-///     let mut instance = s;
-///     builder = builder.add_unary_handler(&METHOD_GREETER_SAY_HELLO, move |ctx, req, resp| {
-///         instance.say_hello(ctx, req, resp)
+///     let mut instance = s.clone();
+///     let limit = options.get_decode_limit("say_hello");
+///     let interceptors = options.interceptors();
+///     builder = builder.add_unary_handler(&::bincode_grpc::bi_codec::raw_request(&GREETER_METHOD_SAY_HELLO), move |ctx, req: Vec<u8>, resp| {
+///         let sink = ::bincode_grpc::UnarySink::new(resp, ::bincode_grpc::bi_codec::check::<_, ::bincode_grpc::bi_codec::Bincode>);
+///         let sink = match interceptors.start(&ctx, Some(req.len())) {
+///             Ok(call) => sink.intercepted(call),
+///             Err(status) => return ::bincode_grpc::handler::reject(&ctx, sink, status),
+///         };
+///         ::bincode_grpc::handler::unary::<_, _, ::bincode_grpc::bi_codec::Bincode, _>(ctx, req, sink, limit, |ctx, req, sink| {
+///             instance.say_hello_grpc(ctx, req, sink)
+///         })
 ///     });
 ///     builder.build()
 /// }
 /// ```
+///
+/// When the methods of the trait take `&self`, a single server is shared by all methods: the
+/// functions take an `Arc<S>` with `S: Greeter + Send + Sync + 'static` and clone the `Arc` instead.
 struct Service {
     attrs: Vec<Attribute>,
     vis: Visibility,
     ident: Ident,
     rpcs: Vec<RpcMethod>,
+    args: ServiceArgs,
+}
+
+/// Options of the `#[service(...)]` attribute
+///
+/// ```
+/// #[service(codec(varint, big_endian, reject_trailing), decode_limit = 1048576)]
+/// ```
+///
+/// `codec = MyCodec` replaces bincode by another `::bincode_grpc::Codec`, `fingerprint` checks
+/// that clients and servers agree on the `::bincode_grpc::Schema` of the messages.
+///
+/// Methods are called on the path `/<package>.<Service>/<Method>`, e.g. `/helloworld.Greeter/SayHello`
+/// with `package = "helloworld"`, or `/Greeter/SayHello` without a package. `legacy_names` also
+/// serves them under the names used by previous versions, e.g. `GREETER_METHOD_SAY_HELLO`, for
+/// clients which are not migrated yet.
+#[derive(Default)]
+struct ServiceArgs {
+    codec: Option<CodecArg>,
+    decode_limit: Option<u64>,
+    fingerprint: bool,
+    package: Option<String>,
+    legacy_names: bool,
+}
+
+enum CodecArg {
+    Bincode(BincodeArgs),
+    Custom(syn::Path),
+}
+
+impl Parse for ServiceArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut args = Self::default();
+        while !input.is_empty() {
+            let key: Ident = input.parse()?;
+            match key.to_string().as_str() {
+                "codec" if input.peek(token::Eq) => {
+                    input.parse::<token::Eq>()?;
+                    args.codec = Some(CodecArg::Custom(input.parse()?));
+                }
+                "codec" => {
+                    let content;
+                    syn::parenthesized!(content in input);
+                    args.codec = Some(CodecArg::Bincode(content.parse()?));
+                }
+                "decode_limit" => {
+                    input.parse::<token::Eq>()?;
+                    args.decode_limit = Some(input.parse::<syn::LitInt>()?.base10_parse()?);
+                }
+                "fingerprint" => args.fingerprint = true,
+                "package" => {
+                    input.parse::<token::Eq>()?;
+                    args.package = Some(input.parse::<syn::LitStr>()?.value());
+                }
+                "legacy_names" => args.legacy_names = true,
+                _ => return Err(syn::Error::new(key.span(), format!("unknown service option `{}`", key))),
+            }
+            if !input.is_empty() {
+                input.parse::<token::Comma>()?;
+            }
+        }
+        Ok(args)
+    }
+}
+
+/// bincode options of `codec(...)`, defaulting to the settings of `bincode::serialize`
+struct BincodeArgs {
+    int_encoding: &'static str,
+    endian: &'static str,
+    trailing: &'static str,
+}
+
+impl Parse for BincodeArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut args = Self {
+            int_encoding: "fixint",
+            endian: "little_endian",
+            trailing: "allow_trailing",
+        };
+        for option in input.parse_terminated::<Ident, token::Comma>(Ident::parse)? {
+            match option.to_string().as_str() {
+                "fixint" => args.int_encoding = "fixint",
+                "varint" => args.int_encoding = "varint",
+                "little_endian" => args.endian = "little_endian",
+                "big_endian" => args.endian = "big_endian",
+                "native_endian" => args.endian = "native_endian",
+                "allow_trailing" => args.trailing = "allow_trailing",
+                "reject_trailing" => args.trailing = "reject_trailing",
+                _ => return Err(syn::Error::new(option.span(), format!("unknown codec option `{}`", option))),
+            }
+        }
+        Ok(args)
+    }
+}
+
+impl BincodeArgs {
+    /// the `BincodeConfig` implementation for `ident`
+    fn config_impl(&self, vis: &Visibility, ident: &Ident) -> TokenStream2 {
+        let (int_encoding, with_int_encoding) = match self.int_encoding {
+            "varint" => (quote::quote! { VarintEncoding }, quote::quote! { with_varint_encoding }),
+            _ => (quote::quote! { FixintEncoding }, quote::quote! { with_fixint_encoding }),
+        };
+        let (endian, with_endian) = match self.endian {
+            "big_endian" => (quote::quote! { BigEndian }, quote::quote! { with_big_endian }),
+            "native_endian" => (quote::quote! { NativeEndian }, quote::quote! { with_native_endian }),
+            _ => (quote::quote! { LittleEndian }, quote::quote! { with_little_endian }),
+        };
+        let (trailing, with_trailing) = match self.trailing {
+            "reject_trailing" => (quote::quote! { RejectTrailing }, quote::quote! { reject_trailing_bytes }),
+            _ => (quote::quote! { AllowTrailing }, quote::quote! { allow_trailing_bytes }),
+        };
+        quote::quote! {
+            #vis struct #ident;
+
+            impl ::bincode_grpc::bi_codec::BincodeConfig for #ident {
+                type Options = ::bincode_grpc::bincode::config::WithOtherTrailing<
+                    ::bincode_grpc::bincode::config::WithOtherEndian<
+                        ::bincode_grpc::bincode::config::WithOtherIntEncoding<
+                            ::bincode_grpc::bincode::DefaultOptions,
+                            ::bincode_grpc::bincode::config::#int_encoding,
+                        >,
+                        ::bincode_grpc::bincode::config::#endian,
+                    >,
+                    ::bincode_grpc::bincode::config::#trailing,
+                >;
+
+                fn options() -> Self::Options {
+                    use ::bincode_grpc::bincode::Options;
+                    ::bincode_grpc::bincode::DefaultOptions::new()
+                        .#with_int_encoding()
+                        .#with_endian()
+                        .#with_trailing()
+                }
+            }
+        }
+    }
 }
 
 impl Parse for Service {
@@ -56,21 +208,53 @@ impl Parse for Service {
         while !content.is_empty() {
             rpcs.push(content.parse()?);
         }
+        if let Some(rpc) = rpcs.iter().find(|rpc| rpc.shared() != rpcs[0].shared()) {
+            return Err(syn::Error::new_spanned(&rpc.receiver, "all methods of a service must take either `&self` or `&mut self`"));
+        }
 
         Ok(Self {
             attrs,
             vis,
             ident,
             rpcs,
+            args: ServiceArgs::default(),
         })
     }
 }
 
 impl Service {
+    /// whether methods take `&self`, sharing a single `Arc<S>` instead of cloning the server per method
+    fn shared(&self) -> bool {
+        self.rpcs.first().is_some_and(RpcMethod::shared)
+    }
+
     fn service_create_fn_ident(&self) -> Ident {
         quote::format_ident!("create_{}", self.ident.to_string().as_str().to_snake_case())
     }
 
+    fn config_ident(&self) -> Ident {
+        quote::format_ident!("{}BincodeConfig", self.ident)
+    }
+
+    /// the `Codec` used by the marshallers of this service
+    fn codec(&self) -> TokenStream2 {
+        match &self.args.codec {
+            Some(CodecArg::Bincode(_)) => {
+                let config_ident = self.config_ident();
+                quote::quote! { ::bincode_grpc::bi_codec::Bincode<#config_ident> }
+            }
+            Some(CodecArg::Custom(codec)) => codec.into_token_stream(),
+            None => quote::quote! { ::bincode_grpc::bi_codec::Bincode },
+        }
+    }
+
+    fn config_impl(&self) -> TokenStream2 {
+        match &self.args.codec {
+            Some(CodecArg::Bincode(codec)) => codec.config_impl(&self.vis, &self.config_ident()),
+            _ => TokenStream2::new(),
+        }
+    }
+
     fn client_ident(&self) -> Ident {
         quote::format_ident!("{}Client", self.ident)
     }
@@ -82,6 +266,9 @@ impl Service {
             #[derive(Clone)]
             #vis struct #ident {
                 client: ::bincode_grpc::grpcio::Client,
+                interceptors: ::bincode_grpc::interceptor::ClientInterceptors,
+                retry_policy: ::bincode_grpc::RetryPolicy,
+                timeout: ::std::option::Option<::std::time::Duration>,
             }
         }
     }
@@ -102,16 +289,18 @@ impl Service {
     fn client_impl(&self) -> TokenStream2 {
         let vis = &self.vis;
         let ident = &self.ident;
+        let codec = self.codec();
         let client_methods: Vec<_> = self
             .rpcs
             .iter()
             .flat_map(|x| {
-                vec![
-                    x.client_method(),
-                    x.client_method_opt(&ident),
-                    x.client_method_async(),
-                    x.client_method_async_opt(&ident),
-                ]
+                let mut methods = vec![x.client_method(), x.client_method_opt(&ident, &x.codec(&codec), self.args.fingerprint)];
+                // streaming calls are asynchronous already
+                if x.kind() == MethodKind::Unary {
+                    methods.push(x.client_method_async());
+                    methods.push(x.client_method_async_opt(&ident, &x.codec(&codec), self.args.fingerprint));
+                }
+                methods
             })
             .collect();
 
@@ -120,19 +309,59 @@ impl Service {
             impl #client_ident {
                 #vis fn new(channel: ::bincode_grpc::grpcio::Channel) -> Self {
                     Self {
-                        client: ::bincode_grpc::grpcio::Client::new(channel)
+                        client: ::bincode_grpc::grpcio::Client::new(channel),
+                        interceptors: ::std::default::Default::default(),
+                        retry_policy: ::std::default::Default::default(),
+                        timeout: None,
                     }
                 }
 
+                /// Sets the timeout of calls made without a `CallOption` to methods declared
+                /// without `#[timeout(...)]`.
+                #vis fn with_timeout(mut self, timeout: ::std::time::Duration) -> Self {
+                    self.timeout = Some(timeout);
+                    self
+                }
+
+                fn default_call_option(&self, timeout: ::std::option::Option<::std::time::Duration>) -> ::bincode_grpc::grpcio::CallOption {
+                    match timeout.or(self.timeout) {
+                        Some(timeout) => ::bincode_grpc::grpcio::CallOption::default().timeout(timeout),
+                        None => ::bincode_grpc::grpcio::CallOption::default(),
+                    }
+                }
+
+                /// Sets the policy retrying the blocking calls of the methods declared with
+                /// `#[idempotent]` or `#[retry(...)]`, see `::bincode_grpc::retry`.
+                #vis fn with_retry_policy(mut self, policy: ::bincode_grpc::RetryPolicy) -> Self {
+                    self.retry_policy = policy;
+                    self
+                }
+
+                /// Adds `interceptor` after the ones already added, see `::bincode_grpc::interceptor`.
+                #vis fn with_interceptor(mut self, interceptor: impl ::bincode_grpc::ClientInterceptor + 'static) -> Self {
+                    self.interceptors.push(interceptor);
+                    self
+                }
+
                 #( #vis #client_methods )*
             }
         }
     }
 
+    /// the path of `rpc` on the wire, `/<package>.<Service>/<Method>`
+    fn method_path(&self, rpc: &RpcMethod) -> String {
+        let service = match &self.args.package {
+            Some(package) => format!("{}.{}", package, self.ident),
+            None => self.ident.to_string(),
+        };
+        format!("/{}/{}", service, rpc.ident.to_string().to_camel_case())
+    }
+
     fn method_declarations(&self) -> TokenStream2 {
         let vis = &self.vis;
         let ident = &self.ident;
-        let method_declarations = self.rpcs.iter().map(|rpc| rpc.method_declaration(&ident));
+        let codec = self.codec();
+        let method_declarations = self.rpcs.iter().map(|rpc| rpc.method_declaration(&ident, &rpc.codec(&codec), &self.method_path(rpc)));
 
         quote::quote! {
             #( #vis #method_declarations )*
@@ -144,34 +373,83 @@ impl Service {
         let vis = &self.vis;
         let ident = &self.ident;
 
-        // let original_fns = self.rpcs.iter().map(|rpc| rpc.original_method());
         let grpc_fns = self.rpcs.iter().map(|rpc| rpc.grpc_method());
 
         quote::quote! {
             #( #attrs )*
             #vis trait #ident {
-                // #( #original_fns )*
                 #( #grpc_fns )*
             }
         }
     }
 
+    fn service_create_with_options_fn_ident(&self) -> Ident {
+        quote::format_ident!("{}_with_options", self.service_create_fn_ident())
+    }
+
     fn create_service(&self) -> TokenStream2 {
         let vis = &self.vis;
         let ident = &self.ident;
         let fn_ident = self.service_create_fn_ident();
+        let with_options_fn_ident = self.service_create_with_options_fn_ident();
+        let service_codec = self.codec();
+        let (instance, server_type, bounds) = if self.shared() {
+            (quote::quote! { instance }, quote::quote! { ::std::sync::Arc<S> }, quote::quote! { Send + Sync })
+        } else {
+            (quote::quote! { mut instance }, quote::quote! { S }, quote::quote! { Send + Clone })
+        };
         let method_registrations = self.rpcs.iter().map(|rpc| {
             let declaration_ident = rpc.method_declaration_ident(ident);
-            let grpc_ident = rpc.grpc_method_ident();
-            quote::quote! {
-                let mut instance = s.clone();
-                builder = builder.add_unary_handler(&#declaration_ident, move |ctx, req, resp| {
-                    instance.#grpc_ident(ctx, req, resp)
-                });
+            let codec = rpc.codec(&service_codec);
+            let method_name = rpc.ident.to_string();
+            let (fingerprint, prelude) = if self.args.fingerprint {
+                let fingerprint = rpc.fingerprint();
+                let full_name = format!("{}.{}", ident, method_name);
+                (
+                    quote::quote! { let fingerprint = #fingerprint; },
+                    quote::quote! {
+                        if let Err(status) = ::bincode_grpc::schema::check_fingerprint(&ctx, #full_name, fingerprint) {
+                            return ::bincode_grpc::handler::reject(&ctx, sink, status);
+                        }
+                    },
+                )
+            } else {
+                (TokenStream2::new(), TokenStream2::new())
+            };
+            let handler = rpc.handler(&codec, &prelude);
+            let add_handler = match rpc.kind() {
+                MethodKind::Unary => quote::quote! { add_unary_handler },
+                MethodKind::ClientStreaming => quote::quote! { add_client_streaming_handler },
+                MethodKind::ServerStreaming => quote::quote! { add_server_streaming_handler },
+                MethodKind::Duplex => quote::quote! { add_duplex_streaming_handler },
+            };
+            let decode_limit = match rpc.method_args.decode_limit.or(self.args.decode_limit) {
+                Some(limit) => quote::quote! { .or(Some(#limit)) },
+                None => TokenStream2::new(),
+            };
+            let register = |method: TokenStream2| {
+                quote::quote! {
+                    let #instance = s.clone();
+                    let limit = options.get_decode_limit(#method_name)#decode_limit;
+                    let interceptors = options.interceptors();
+                    #fingerprint
+                    builder = builder.#add_handler(&::bincode_grpc::bi_codec::raw_request(&#method), #handler);
+                }
+            };
+            let mut registration = register(declaration_ident.to_token_stream());
+            if self.args.legacy_names {
+                registration.extend(register(quote::quote! {
+                    ::bincode_grpc::grpcio::Method { name: stringify!(#declaration_ident), ..#declaration_ident }
+                }));
             }
+            registration
         });
         quote::quote! {
-            #vis fn #fn_ident<S: #ident + Send + Clone + 'static>(s: S) -> ::bincode_grpc::grpcio::Service {
+            #vis fn #fn_ident<S: #ident + #bounds + 'static>(s: #server_type) -> ::bincode_grpc::grpcio::Service {
+                #with_options_fn_ident(s, ::bincode_grpc::ServiceOptions::default())
+            }
+
+            #vis fn #with_options_fn_ident<S: #ident + #bounds + 'static>(s: #server_type, options: ::bincode_grpc::ServiceOptions) -> ::bincode_grpc::grpcio::Service {
                 let mut builder = ::bincode_grpc::grpcio::ServiceBuilder::new();
                 #( #method_registrations )*
                 builder.build()
@@ -183,6 +461,7 @@ impl Service {
 impl ToTokens for Service {
     fn to_tokens(&self, tokens: &mut TokenStream2) {
         tokens.extend(vec![
+            self.config_impl(),
             self.method_declarations(),
             self.trait_service(),
             self.create_service(),
@@ -198,7 +477,7 @@ impl ToTokens for Service {
 /// ```
 /// pub const METHOD_GREETER_SAY_HELLO: grpcio::Method<HelloRequest, HelloReply> = grpcio::Method {
 ///     ty: MethodType::Unary,
-///     name: "hello",
+///     name: "/Greeter/SayHello",
 ///     req_mar: Marshaller {
 ///         ser: grpcio::bi_ser,
 ///         de: grpcio::bi_de,
@@ -224,52 +503,504 @@ impl ToTokens for Service {
 ///         &mut self,
 ///         ctx: ::bincode_grpc::grpcio::RpcContext,
 ///         req: (HelloRequest, ), // the tuple is used for the case where the original trait method has multiple arguments
-///         sink: ::bincode_grpc::grpcio::UnarySink<HelloReply>,
+///         sink: ::bincode_grpc::UnarySink<HelloReply>,
 ///     );
 /// ```
+///
+/// Methods returning `ServerStream<T>` are server streaming, their `*_grpc` method takes a
+/// `::bincode_grpc::ServerStreamingSink<T>` instead. Methods taking a `ClientStream<T>` as their
+/// only argument are client streaming, or bidirectional streaming if they also return a
+/// `ServerStream<U>`. Unless they are `async`, client streaming methods block on the requests and
+/// run on the blocking pool of the `#[server]` impl, so they must take `&self`.
 struct RpcMethod {
     attrs: Vec<Attribute>,
     ident: Ident,
     args: Vec<syn::PatType>,
     receiver: syn::Receiver,
     output: ReturnType,
+    method_args: MethodArgs,
+    asyncness: Option<token::Async>,
+    /// the `&CallInfo` argument, if any, which is not part of the request
+    call_info: Option<syn::PatType>,
+}
+
+/// Options given as attributes on a trait method
+///
+/// ```ignore
+/// #[decode_limit(4096)]
+/// #[compress(zstd, min_size = 1024)]
+/// fn say_hello(&mut self, arg1: HelloRequest) -> HelloReply;
+/// ```
+///
+/// `#[status]` on a method returning `Result<T, E>` with `E: ::bincode_grpc::RpcError` sends
+/// errors as gRPC statuses instead of serializing them.
+///
+/// `#[idempotent]` and `#[retry(...)]` mark unary methods as safe to retry by blocking client
+/// calls, see `::bincode_grpc::retry`. Their `*_async` client methods are not retried.
+///
+/// `#[timeout(ms = 500)]` sets the timeout of client calls made without a `CallOption`, taking
+/// precedence over the one set with `with_timeout` on the client.
+#[derive(Default)]
+struct MethodArgs {
+    decode_limit: Option<u64>,
+    compress: Option<CompressArgs>,
+    status: bool,
+    retry: Option<RetryArgs>,
+    timeout_ms: Option<u64>,
+}
+
+/// `#[timeout(ms = 500)]`, the default timeout of client calls
+struct TimeoutArgs {
+    ms: u64,
+}
+
+impl Parse for TimeoutArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let key: Ident = input.parse()?;
+        if key != "ms" {
+            return Err(syn::Error::new(key.span(), format!("unknown timeout option `{}`, expected `ms`", key)));
+        }
+        input.parse::<token::Eq>()?;
+        let ms = input.parse::<syn::LitInt>()?.base10_parse()?;
+        Ok(Self { ms })
+    }
+}
+
+/// `#[idempotent]` or `#[retry(max_attempts = 5, initial_backoff_ms = 100, codes(UNAVAILABLE))]`,
+/// the calls overriding the `RetryPolicy` of the client
+#[derive(Default)]
+struct RetryArgs {
+    overrides: Vec<TokenStream2>,
+}
+
+impl Parse for RetryArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut overrides = vec![];
+        while !input.is_empty() {
+            let key: Ident = input.parse()?;
+            let value = match key.to_string().as_str() {
+                "codes" => {
+                    let content;
+                    syn::parenthesized!(content in input);
+                    let codes = content.parse_terminated::<Ident, token::Comma>(Ident::parse)?;
+                    let codes = codes.iter();
+                    quote::quote! { retry_on(&[#( ::bincode_grpc::grpcio::RpcStatusCode::#codes ),*]) }
+                }
+                "max_attempts" => {
+                    input.parse::<token::Eq>()?;
+                    let value: syn::LitInt = input.parse()?;
+                    quote::quote! { max_attempts(#value) }
+                }
+                "initial_backoff_ms" | "max_backoff_ms" => {
+                    input.parse::<token::Eq>()?;
+                    let value: syn::LitInt = input.parse()?;
+                    let setter = quote::format_ident!("{}", key.to_string().trim_end_matches("_ms"));
+                    quote::quote! { #setter(::std::time::Duration::from_millis(#value)) }
+                }
+                "multiplier" | "jitter" => {
+                    input.parse::<token::Eq>()?;
+                    let value: syn::LitFloat = input.parse()?;
+                    quote::quote! { #key(#value) }
+                }
+                _ => return Err(syn::Error::new(key.span(), format!("unknown retry option `{}`", key))),
+            };
+            overrides.push(value);
+            if !input.is_empty() {
+                input.parse::<token::Comma>()?;
+            }
+        }
+        Ok(Self { overrides })
+    }
+}
+
+/// `#[compress(algorithm, min_size = N)]`
+struct CompressArgs {
+    compressor: TokenStream2,
+    min_size: usize,
+}
+
+impl Parse for CompressArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let algorithm: Ident = input.parse()?;
+        let compressor = match algorithm.to_string().as_str() {
+            "zstd" => quote::quote! { ::bincode_grpc::bi_codec::Zstd },
+            "lz4" => quote::quote! { ::bincode_grpc::bi_codec::Lz4 },
+            _ => return Err(syn::Error::new(algorithm.span(), format!("unknown compression algorithm `{}`", algorithm))),
+        };
+        let mut min_size = 0;
+        while !input.is_empty() {
+            input.parse::<token::Comma>()?;
+            if input.is_empty() {
+                break;
+            }
+            let key: Ident = input.parse()?;
+            if key != "min_size" {
+                return Err(syn::Error::new(key.span(), format!("unknown compression option `{}`", key)));
+            }
+            input.parse::<token::Eq>()?;
+            min_size = input.parse::<syn::LitInt>()?.base10_parse()?;
+        }
+        Ok(Self { compressor, min_size })
+    }
+}
+
+impl MethodArgs {
+    /// takes the attributes recognized as method options out of `attrs`
+    fn extract(attrs: &mut Vec<Attribute>) -> syn::Result<Self> {
+        let mut args = Self::default();
+        let mut remaining = vec![];
+        for attr in attrs.drain(..) {
+            if attr.path.is_ident("decode_limit") {
+                args.decode_limit = Some(attr.parse_args::<syn::LitInt>()?.base10_parse()?);
+            } else if attr.path.is_ident("compress") {
+                args.compress = Some(attr.parse_args()?);
+            } else if attr.path.is_ident("status") {
+                if !attr.tokens.is_empty() {
+                    return Err(syn::Error::new_spanned(&attr.tokens, "`#[status]` takes no arguments"));
+                }
+                args.status = true;
+            } else if attr.path.is_ident("idempotent") {
+                if !attr.tokens.is_empty() {
+                    return Err(syn::Error::new_spanned(&attr.tokens, "`#[idempotent]` takes no arguments, use `#[retry(...)]`"));
+                }
+                args.retry.get_or_insert_with(RetryArgs::default);
+            } else if attr.path.is_ident("retry") {
+                args.retry = Some(attr.parse_args()?);
+            } else if attr.path.is_ident("timeout") {
+                args.timeout_ms = Some(attr.parse_args::<TimeoutArgs>()?.ms);
+            } else {
+                remaining.push(attr);
+            }
+        }
+        *attrs = remaining;
+        Ok(args)
+    }
+}
+
+/// `T` of `ty` if it is `wrapper<T>`, e.g. `ServerStream<T>`
+fn wrapped_type<'a>(ty: &'a syn::Type, wrapper: &str) -> Option<&'a syn::Type> {
+    let segment = match ty {
+        syn::Type::Path(p) if p.qself.is_none() => p.path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != wrapper {
+        return None;
+    }
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) if args.args.len() == 1 => match args.args.first()? {
+            syn::GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// whether `ty` is `&CallInfo`, an argument filled in by the server instead of being sent by clients
+fn is_call_info(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Reference(r) if r.mutability.is_none() => match &*r.elem {
+            syn::Type::Path(p) if p.qself.is_none() => p.path.segments.last().is_some_and(|s| s.ident == "CallInfo"),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// How requests and responses are exchanged by a method
+#[derive(Clone, Copy, PartialEq)]
+enum MethodKind {
+    Unary,
+    /// taking a `ClientStream<T>` as its only argument
+    ClientStreaming,
+    /// returning a `ServerStream<T>`
+    ServerStreaming,
+    /// taking a `ClientStream<T>` and returning a `ServerStream<U>`
+    Duplex,
+}
+
+/// How an RPC argument is decoded on the server
+///
+/// `&str` and `&[u8]` borrow from the received request and `bytes::Bytes` shares its buffer,
+/// clients send them as `String` and `Vec<u8>` which have the same encoding. `Bytes` is only
+/// recognized with its path, other `Bytes` types are owned. Other types with lifetimes, e.g.
+/// `Req<'_>`, borrow from the request too and clients send them as they are.
+#[derive(Clone, Copy, PartialEq)]
+enum ArgKind {
+    Owned,
+    Str,
+    Slice,
+    Bytes,
+    Borrowed,
+}
+
+/// rejects the argument types which cannot be decoded from requests
+fn check_arg_type(ty: &syn::Type) -> syn::Result<()> {
+    match ty {
+        syn::Type::Reference(_) if ArgKind::of(ty) == ArgKind::Owned => {
+            Err(syn::Error::new_spanned(ty, "RPC arguments can only borrow `&str` and `&[u8]` from the request, take this one by value"))
+        }
+        syn::Type::ImplTrait(_) => Err(syn::Error::new_spanned(ty, "`impl Trait` is not supported in RPC arguments, use a concrete type")),
+        _ => Ok(()),
+    }
+}
+
+/// rejects the return types which cannot be encoded into responses
+fn check_output_type(output: &ReturnType) -> syn::Result<()> {
+    match output {
+        ReturnType::Type(_, ty) => match &**ty {
+            syn::Type::Reference(_) => Err(syn::Error::new_spanned(ty, "RPC methods must return owned values, not references")),
+            syn::Type::ImplTrait(_) => Err(syn::Error::new_spanned(ty, "`impl Trait` is not supported in RPC return types, use a concrete type")),
+            _ => Ok(()),
+        },
+        ReturnType::Default => Ok(()),
+    }
+}
+
+impl ArgKind {
+    fn of(ty: &syn::Type) -> Self {
+        match ty {
+            syn::Type::Path(p) if is_bytes_path(p) => ArgKind::Bytes,
+            syn::Type::Reference(_) => Self::of_reference(ty),
+            _ if with_lifetime(ty, "'_").is_some() => ArgKind::Borrowed,
+            _ => ArgKind::Owned,
+        }
+    }
+
+    fn of_reference(ty: &syn::Type) -> Self {
+        match ty {
+            syn::Type::Reference(r) if r.mutability.is_none() => match &*r.elem {
+                syn::Type::Path(p) if p.qself.is_none() && p.path.is_ident("str") => ArgKind::Str,
+                syn::Type::Slice(s) => match &*s.elem {
+                    syn::Type::Path(p) if p.qself.is_none() && p.path.is_ident("u8") => ArgKind::Slice,
+                    _ => ArgKind::Owned,
+                },
+                _ => ArgKind::Owned,
+            },
+            _ => ArgKind::Owned,
+        }
+    }
+
+    /// whether the decoded argument refers to the request
+    fn borrows(self) -> bool {
+        matches!(self, ArgKind::Str | ArgKind::Slice | ArgKind::Borrowed)
+    }
+
+    /// the type of the argument in the requests sent by clients
+    fn client_type(self, ty: &syn::Type) -> TokenStream2 {
+        match self {
+            ArgKind::Owned => ty.into_token_stream(),
+            ArgKind::Str => quote::quote! { ::std::string::String },
+            ArgKind::Slice | ArgKind::Bytes => quote::quote! { ::std::vec::Vec<u8> },
+            ArgKind::Borrowed => with_lifetime(ty, "'static").into_token_stream(),
+        }
+    }
+
+    /// the type client methods take the argument as, a scalar or an `impl` of the trait borrowing
+    /// a type which has the encoding of `client_type`, the lifetimes of `Borrowed` arguments are
+    /// the `'req` of the client method
+    fn client_param_type(self, ty: &syn::Type) -> TokenStream2 {
+        match self {
+            ArgKind::Owned if is_scalar(ty) => ty.into_token_stream(),
+            ArgKind::Owned => quote::quote! { impl ::std::borrow::Borrow<#ty> },
+            ArgKind::Borrowed => {
+                let ty = with_lifetime(ty, "'req");
+                quote::quote! { impl ::std::borrow::Borrow<#ty> }
+            }
+            ArgKind::Str => quote::quote! { impl ::std::convert::AsRef<str> },
+            ArgKind::Slice | ArgKind::Bytes => quote::quote! { impl ::std::convert::AsRef<[u8]> },
+        }
+    }
+
+    /// borrows the client argument `name` taken as `client_param_type`
+    fn client_borrow(self, ty: &syn::Type, name: &Ident) -> TokenStream2 {
+        match self {
+            ArgKind::Owned if is_scalar(ty) => quote::quote! { &#name },
+            ArgKind::Owned => quote::quote! { ::std::borrow::Borrow::<#ty>::borrow(&#name) },
+            ArgKind::Borrowed => {
+                let ty = with_lifetime(ty, "'req");
+                quote::quote! { ::std::borrow::Borrow::<#ty>::borrow(&#name) }
+            }
+            ArgKind::Str => quote::quote! { ::std::convert::AsRef::<str>::as_ref(&#name) },
+            ArgKind::Slice | ArgKind::Bytes => quote::quote! { ::std::convert::AsRef::<[u8]>::as_ref(&#name) },
+        }
+    }
+
+    /// the type of the argument when decoding requests on the server
+    fn decoded_type(self, ty: &syn::Type) -> TokenStream2 {
+        match self {
+            ArgKind::Bytes => quote::quote! { &[u8] },
+            _ => ty.into_token_stream(),
+        }
+    }
+}
+
+/// whether `ty` is a primitive scalar, which client methods take by value so that literals keep
+/// their type inference: `client.add(2)` cannot infer the integer type of `impl Borrow<u64>`
+fn is_scalar(ty: &syn::Type) -> bool {
+    const SCALARS: &[&str] = &[
+        "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize", "f32", "f64", "bool", "char",
+    ];
+    match ty {
+        syn::Type::Path(p) => p.qself.is_none() && SCALARS.iter().any(|scalar| p.path.is_ident(scalar)),
+        _ => false,
+    }
+}
+
+/// whether `p` is `bytes::Bytes`, also reexported as `bincode_grpc::bytes::Bytes`
+fn is_bytes_path(p: &syn::TypePath) -> bool {
+    let idents: Vec<_> = p.path.segments.iter().map(|s| s.ident.to_string()).collect();
+    p.qself.is_none()
+        && p.path.segments.iter().all(|s| s.arguments.is_empty())
+        && matches!(idents.iter().map(String::as_str).collect::<Vec<_>>()[..], ["bytes", "Bytes"] | ["bincode_grpc", "bytes", "Bytes"])
+}
+
+/// `ty` with all its lifetimes, named or elided, replaced by `lifetime`, or `None` if it has
+/// none
+fn with_lifetime(ty: &syn::Type, lifetime: &str) -> Option<syn::Type> {
+    fn replace(ty: &mut syn::Type, lifetime: &syn::Lifetime) -> bool {
+        match ty {
+            syn::Type::Reference(r) => {
+                r.lifetime = Some(lifetime.clone());
+                replace(&mut r.elem, lifetime);
+                true
+            }
+            syn::Type::Path(p) => {
+                let mut found = false;
+                if let Some(qself) = &mut p.qself {
+                    found |= replace(&mut qself.ty, lifetime);
+                }
+                for segment in &mut p.path.segments {
+                    if let syn::PathArguments::AngleBracketed(args) = &mut segment.arguments {
+                        for arg in &mut args.args {
+                            match arg {
+                                syn::GenericArgument::Lifetime(l) => {
+                                    *l = lifetime.clone();
+                                    found = true;
+                                }
+                                syn::GenericArgument::Type(ty) => found |= replace(ty, lifetime),
+                                _ => {}
+                            }
+                        }
+                    }
+                }
+                found
+            }
+            syn::Type::Slice(s) => replace(&mut s.elem, lifetime),
+            syn::Type::Array(a) => replace(&mut a.elem, lifetime),
+            syn::Type::Paren(p) => replace(&mut p.elem, lifetime),
+            syn::Type::Group(g) => replace(&mut g.elem, lifetime),
+            syn::Type::Tuple(t) => t.elems.iter_mut().fold(false, |found, ty| replace(ty, lifetime) | found),
+            _ => false,
+        }
+    }
+    let mut ty = ty.clone();
+    replace(&mut ty, &syn::Lifetime::new(lifetime, Span::call_site())).then_some(ty)
 }
 
 impl Parse for RpcMethod {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let attrs = input.call(Attribute::parse_outer)?;
+        let mut attrs = input.call(Attribute::parse_outer)?;
+        let method_args = MethodArgs::extract(&mut attrs)?;
+        if let Some(constness) = input.parse::<Option<token::Const>>()? {
+            return Err(syn::Error::new_spanned(constness, "RPC methods cannot be `const`"));
+        }
+        let asyncness: Option<token::Async> = input.parse()?;
+        if let Some(unsafety) = input.parse::<Option<token::Unsafe>>()? {
+            return Err(syn::Error::new_spanned(unsafety, "RPC methods cannot be `unsafe`"));
+        }
+        if let Some(abi) = input.parse::<Option<syn::Abi>>()? {
+            return Err(syn::Error::new_spanned(abi, "RPC methods cannot have an `extern` ABI"));
+        }
         input.parse::<token::Fn>()?;
         let ident: Ident = input.parse()?;
+        let generics: syn::Generics = input.parse()?;
+        if !generics.params.is_empty() {
+            return Err(syn::Error::new_spanned(generics, "RPC methods cannot be generic, the types of the messages must be known"));
+        }
         let content;
-        syn::parenthesized!(content in input);
+        let paren = syn::parenthesized!(content in input);
         let mut args = vec![];
         let mut receiver = None;
+        let mut call_info = None;
         for arg in content.parse_terminated::<syn::FnArg, token::Comma>(syn::FnArg::parse)? {
             match arg {
                 FnArg::Receiver(captures) => {
-                    if captures.mutability.is_none() {
-                        panic!("should be &mut self");
+                    if captures.reference.is_none() {
+                        return Err(syn::Error::new_spanned(captures, "RPC methods must take `&self` or `&mut self`, not `self`"));
                     } else if receiver.is_some() {
-                        panic!("duplicated self");
+                        return Err(syn::Error::new_spanned(captures, "duplicated `self` argument"));
                     } else {
                         receiver = Some(captures);
                     }
                 }
-                FnArg::Typed(captures) => match *captures.pat {
-                    syn::Pat::Ident(_) => args.push(captures),
-                    _ => panic!("patterns aren't allowd in RPC args"),
+                FnArg::Typed(captures) => match &*captures.pat {
+                    syn::Pat::Ident(pat) if pat.ident == "self" => {
+                        return Err(syn::Error::new_spanned(captures, "RPC methods must take `&self` or `&mut self`"));
+                    }
+                    syn::Pat::Ident(_) if is_call_info(&captures.ty) => {
+                        if call_info.is_some() {
+                            return Err(syn::Error::new_spanned(captures, "duplicated `&CallInfo` argument"));
+                        }
+                        call_info = Some(captures);
+                    }
+                    syn::Pat::Ident(_) => {
+                        check_arg_type(&captures.ty)?;
+                        args.push(captures);
+                    }
+                    pat => return Err(syn::Error::new_spanned(pat, "patterns are not supported in RPC arguments, bind the argument to a name")),
                 },
             }
         }
+        let receiver = match receiver {
+            Some(receiver) => receiver,
+            None => return Err(syn::Error::new(paren.span, "RPC methods must take `&self` or `&mut self`")),
+        };
         let output: syn::ReturnType = input.parse()?;
+        check_output_type(&output)?;
+        if let Some(where_clause) = input.parse::<Option<syn::WhereClause>>()? {
+            return Err(syn::Error::new_spanned(where_clause, "RPC methods cannot have a `where` clause"));
+        }
+        if input.peek(token::Brace) {
+            return Err(input.error("RPC methods cannot have a default implementation, implement them with `#[server]`"));
+        }
         input.parse::<token::Semi>()?;
-        Ok(Self {
+        let method = Self {
             attrs,
             ident,
             args,
-            receiver: receiver.unwrap(),
+            receiver,
             output,
-        })
+            method_args,
+            asyncness,
+            call_info,
+        };
+        if method.method_args.status && method.status_types().is_none() {
+            return Err(syn::Error::new_spanned(&method.output, "`#[status]` methods must return `Result<T, E>`"));
+        }
+        if method.args.len() > 1 && method.args.iter().any(|arg| wrapped_type(&arg.ty, "ClientStream").is_some()) {
+            return Err(syn::Error::new(method.ident.span(), "a `ClientStream` must be the only argument of the method"));
+        }
+        if method.method_args.status && method.kind() != MethodKind::Unary {
+            return Err(syn::Error::new_spanned(&method.output, "`#[status]` is only supported on unary methods, use `ServerStream::try_new`"));
+        }
+        if method.method_args.retry.is_some() && method.kind() != MethodKind::Unary {
+            return Err(syn::Error::new(method.ident.span(), "only unary methods can be retried"));
+        }
+        if method.asyncness.is_some() && method.receiver.mutability.is_some() {
+            return Err(mut_self_error(&method.receiver, "`async` methods"));
+        }
+        if method.client_stream_item().is_some() && method.receiver.mutability.is_some() {
+            return Err(mut_self_error(&method.receiver, "client streaming methods"));
+        }
+        if method.asyncness.is_some() && method.args.iter().any(|arg| ArgKind::of(&arg.ty).borrows()) {
+            return Err(syn::Error::new_spanned(method.asyncness, "`async` methods outlive the request and cannot borrow from it, use `bytes::Bytes` instead"));
+        }
+        if method.method_args.compress.is_some() {
+            if let Some(arg) = method.args.iter().find(|arg| ArgKind::of(&arg.ty) != ArgKind::Owned) {
+                return Err(syn::Error::new_spanned(&arg.ty, "`#[compress]` methods decode requests from a decompressed copy and cannot borrow from them, take this argument as an owned type"));
+            }
+        }
+        Ok(method)
     }
 }
 
@@ -283,24 +1014,21 @@ impl RpcMethod {
         )
     }
 
+    /// the `Codec` of this method, `service_codec` wrapped into compressed frames with `#[compress(...)]`
+    fn codec(&self, service_codec: &TokenStream2) -> TokenStream2 {
+        match &self.method_args.compress {
+            Some(CompressArgs { compressor, min_size }) => quote::quote! {
+                ::bincode_grpc::bi_codec::Compressed<#service_codec, #compressor, #min_size>
+            },
+            None => service_codec.clone(),
+        }
+    }
+
     /// `method` to `method_grpc`
     fn grpc_method_ident(&self) -> Ident {
         quote::format_ident!("{}_grpc", self.ident)
     }
 
-    /// original user defined methods
-    fn original_method(&self) -> TokenStream2 {
-        let attrs = &self.attrs;
-        let ident = &self.ident;
-        let args = &self.args;
-        let receiver = &self.receiver;
-        let output = &self.output;
-        quote::quote! {
-            #( #attrs )*
-            fn #ident(#receiver, #( #args ),*) #output;
-        }
-    }
-
     fn req_type(&self) -> TokenStream2 {
         let args = &self.args;
         let all_arg_types: Vec<_> = args.iter().map(|x| &x.ty).collect();
@@ -315,6 +1043,190 @@ impl RpcMethod {
         }
     }
 
+    /// the request type of clients, with borrowed arguments replaced by their owned counterpart
+    fn client_req_type(&self) -> TokenStream2 {
+        if let Some(item) = self.client_stream_item() {
+            return item.into_token_stream();
+        }
+        let arg_types: Vec<_> = self.args.iter().map(|x| ArgKind::of(&x.ty).client_type(&x.ty)).collect();
+        if arg_types.is_empty() {
+            quote::quote! { () }
+        } else {
+            quote::quote! { (#( #arg_types ),*,) }
+        }
+    }
+
+    /// whether some arguments borrow from the request, see `ArgKind`
+    fn borrows_request(&self) -> bool {
+        self.args.iter().any(|x| ArgKind::of(&x.ty) != ArgKind::Owned)
+    }
+
+    /// the closure passed to `add_*_handler`, decoding requests within `limit` bytes after
+    /// running `interceptors` and `prelude`
+    fn handler(&self, codec: &TokenStream2, prelude: &TokenStream2) -> TokenStream2 {
+        let grpc_ident = self.grpc_method_ident();
+        let sink = self.sink(codec);
+        let intercept = |size: TokenStream2| {
+            quote::quote! {
+                let sink = match interceptors.start(&ctx, #size) {
+                    Ok(call) => sink.intercepted(call),
+                    Err(status) => return ::bincode_grpc::handler::reject(&ctx, sink, status),
+                };
+            }
+        };
+        if self.client_stream_item().is_some() {
+            let intercept = intercept(quote::quote! { None });
+            return quote::quote! {
+                move |ctx, stream, resp| {
+                    let sink = #sink;
+                    #intercept
+                    #prelude
+                    let stream = ::bincode_grpc::ClientStream::new::<#codec>(stream, limit);
+                    instance.#grpc_ident(ctx, (stream,), sink)
+                }
+            };
+        }
+        if !self.borrows_request() {
+            let intercept = intercept(quote::quote! { Some(req.len()) });
+            return quote::quote! {
+                move |ctx, req: ::std::vec::Vec<u8>, resp| {
+                    let sink = #sink;
+                    #intercept
+                    #prelude
+                    ::bincode_grpc::handler::unary::<_, _, #codec, _>(ctx, req, sink, limit, |ctx, req, sink| {
+                        instance.#grpc_ident(ctx, req, sink)
+                    })
+                }
+            };
+        }
+        let kinds: Vec<_> = self.args.iter().map(|x| ArgKind::of(&x.ty)).collect();
+        let decoded_types = self.args.iter().zip(&kinds).map(|(x, kind)| kind.decoded_type(&x.ty));
+        let bindings: Vec<_> = (0..kinds.len()).map(|i| quote::format_ident!("arg{}", i)).collect();
+        let values = bindings.iter().zip(&kinds).map(|(binding, kind)| match kind {
+            ArgKind::Bytes => quote::quote! { payload.slice_ref(#binding) },
+            _ => binding.into_token_stream(),
+        });
+        // the arguments are done with the payload once the handler returns, `Bytes` arguments
+        // share a copy of it instead so that the buffer is recycled right away
+        let (payload, recycle) = if kinds.contains(&ArgKind::Bytes) {
+            (
+                quote::quote! {
+                    let shared = ::bincode_grpc::bytes::Bytes::copy_from_slice(&payload);
+                    ::bincode_grpc::bi_codec::recycle(payload);
+                    let payload = shared;
+                },
+                TokenStream2::new(),
+            )
+        } else {
+            (TokenStream2::new(), quote::quote! { ::bincode_grpc::bi_codec::recycle(payload); })
+        };
+        let intercept = intercept(quote::quote! { Some(payload.len()) });
+        quote::quote! {
+            move |ctx, payload: ::std::vec::Vec<u8>, resp| {
+                let sink = #sink;
+                #intercept
+                #prelude
+                #payload
+                match ::bincode_grpc::bi_codec::decode_borrowed::<(#( #decoded_types ),*,), #codec>(&payload, limit) {
+                    Ok((#( #bindings ),*,)) => instance.#grpc_ident(ctx, (#( #values ),*,), sink),
+                    Err(status) => ::bincode_grpc::handler::reject(&ctx, sink, status),
+                }
+                #recycle
+            }
+        }
+    }
+
+    /// the sink wrapping the grpcio sink `resp`
+    fn sink(&self, codec: &TokenStream2) -> TokenStream2 {
+        if self.server_stream_item().is_some() {
+            return quote::quote! {
+                ::bincode_grpc::ServerStreamingSink::new(resp, ::bincode_grpc::bi_codec::check::<_, #codec>)
+            };
+        }
+        match self.status_types() {
+            Some((ok, err)) => quote::quote! {
+                ::bincode_grpc::UnarySink::with_reply(
+                    resp,
+                    ::bincode_grpc::bi_codec::check_ok::<#ok, #err, #codec>,
+                    ::bincode_grpc::status::into_reply,
+                )
+            },
+            None => quote::quote! {
+                ::bincode_grpc::UnarySink::new(resp, ::bincode_grpc::bi_codec::check::<_, #codec>)
+            },
+        }
+    }
+
+    /// the sink type of the generated `*_grpc` method
+    fn sink_type(&self) -> TokenStream2 {
+        match self.server_stream_item() {
+            Some(item) => quote::quote! { ::bincode_grpc::ServerStreamingSink<#item> },
+            None => {
+                let resp_type = self.resp_type();
+                quote::quote! { ::bincode_grpc::UnarySink<#resp_type> }
+            }
+        }
+    }
+
+    fn kind(&self) -> MethodKind {
+        match (self.client_stream_item(), self.server_stream_item()) {
+            (None, None) => MethodKind::Unary,
+            (Some(_), None) => MethodKind::ClientStreaming,
+            (None, Some(_)) => MethodKind::ServerStreaming,
+            (Some(_), Some(_)) => MethodKind::Duplex,
+        }
+    }
+
+    /// `T` of client streaming methods, taking a `ClientStream<T>`
+    fn client_stream_item(&self) -> Option<&syn::Type> {
+        match self.args.as_slice() {
+            [arg] => wrapped_type(&arg.ty, "ClientStream"),
+            _ => None,
+        }
+    }
+
+    /// `T` of server streaming methods, returning `ServerStream<T>`
+    fn server_stream_item(&self) -> Option<&syn::Type> {
+        match &self.output {
+            ReturnType::Type(_, ty) => wrapped_type(ty, "ServerStream"),
+            ReturnType::Default => None,
+        }
+    }
+
+    /// the response type of the method declaration, the items of server streaming methods
+    fn declared_resp_type(&self) -> TokenStream2 {
+        match self.server_stream_item() {
+            Some(item) => item.into_token_stream(),
+            None => self.resp_type(),
+        }
+    }
+
+    /// `T` and `E` of methods declared with `#[status]`, returning `Result<T, E>`
+    fn status_types(&self) -> Option<(&syn::Type, &syn::Type)> {
+        if !self.method_args.status {
+            return None;
+        }
+        let ty = match &self.output {
+            ReturnType::Type(_, ty) => &**ty,
+            ReturnType::Default => return None,
+        };
+        let segment = match ty {
+            syn::Type::Path(p) if p.qself.is_none() => p.path.segments.last()?,
+            _ => return None,
+        };
+        if segment.ident != "Result" {
+            return None;
+        }
+        let args = match &segment.arguments {
+            syn::PathArguments::AngleBracketed(args) => &args.args,
+            _ => return None,
+        };
+        match (args.first(), args.iter().nth(1), args.len()) {
+            (Some(syn::GenericArgument::Type(ok)), Some(syn::GenericArgument::Type(err)), 2) => Some((ok, err)),
+            _ => None,
+        }
+    }
+
     fn resp_type(&self) -> TokenStream2 {
         let output = &self.output;
         let output = match output {
@@ -326,67 +1238,275 @@ impl RpcMethod {
         }
     }
 
+    /// the result of synchronous client methods, a stream of responses for server streaming
+    /// methods and a request sender with the response receiver for client and bidirectional
+    /// streaming methods
+    fn client_resp_type(&self) -> TokenStream2 {
+        let resp_type = self.declared_resp_type();
+        let req_type = self.client_req_type();
+        match self.kind() {
+            MethodKind::Unary => resp_type,
+            MethodKind::ClientStreaming => quote::quote! {
+                (
+                    ::bincode_grpc::StreamSender<#req_type>,
+                    ::bincode_grpc::interceptor::Intercepted<::bincode_grpc::grpcio::ClientCStreamReceiver<#resp_type>>,
+                )
+            },
+            MethodKind::ServerStreaming => quote::quote! {
+                ::bincode_grpc::interceptor::Intercepted<::bincode_grpc::grpcio::ClientSStreamReceiver<#resp_type>>
+            },
+            MethodKind::Duplex => quote::quote! {
+                (
+                    ::bincode_grpc::StreamSender<#req_type>,
+                    ::bincode_grpc::interceptor::Intercepted<::bincode_grpc::grpcio::ClientDuplexReceiver<#resp_type>>,
+                )
+            },
+        }
+    }
+
+    /// the request parameters of client methods and their forwarding, client streaming methods
+    /// send their requests afterwards
+    ///
+    /// Arguments are taken by value or by reference as `impl Borrow<T>`, primitive scalars by
+    /// value, and as `impl AsRef<str>` or `impl AsRef<[u8]>` for the ones the server borrows from
+    /// the request. Borrowed structs are taken as `impl Borrow<T<'req>>`, see `client_generics`.
+    fn client_req_param(&self) -> (TokenStream2, TokenStream2) {
+        if self.client_stream_item().is_some() {
+            return (TokenStream2::new(), TokenStream2::new());
+        }
+        let names: Vec<_> = self.args.iter().map(Self::arg_name).collect();
+        let types = self.args.iter().map(|x| ArgKind::of(&x.ty).client_param_type(&x.ty));
+        (
+            quote::quote! { #( #names: #types, )* },
+            quote::quote! { #( #names, )* },
+        )
+    }
+
+    /// the generics of client methods, the lifetime of the arguments the server decodes as borrowed
+    /// structs
+    fn client_generics(&self) -> TokenStream2 {
+        if self.client_stream_item().is_none() && self.args.iter().any(|x| ArgKind::of(&x.ty) == ArgKind::Borrowed) {
+            quote::quote! { <'req> }
+        } else {
+            TokenStream2::new()
+        }
+    }
+
+    /// the identifier bound by an argument, without `mut`
+    fn arg_name(arg: &syn::PatType) -> &Ident {
+        match &*arg.pat {
+            syn::Pat::Ident(pat) => &pat.ident,
+            _ => unreachable!("patterns are rejected when parsing"),
+        }
+    }
+
+    /// encodes the arguments of client methods into `payload`, the tuple of borrowed arguments
+    /// has the encoding of the request
+    fn client_payload(&self, codec: &TokenStream2) -> TokenStream2 {
+        let borrows = self.args.iter().map(|x| ArgKind::of(&x.ty).client_borrow(&x.ty, Self::arg_name(x)));
+        quote::quote! {
+            let req = (#( #borrows, )*);
+            let payload = ::bincode_grpc::bi_codec::encode::<_, #codec>(&req)?;
+        }
+    }
+
     fn client_method(&self) -> TokenStream2 {
         let ident = &self.ident;
-        let req_type = self.req_type();
-        let resp_type = self.resp_type();
+        let (req_param, req_arg) = self.client_req_param();
+        let generics = self.client_generics();
+        let resp_type = self.client_resp_type();
         let opt_method_ident = quote::format_ident!("{}_opt", ident);
 
+        let default_opt = self.default_call_option();
+
         quote::quote! {
-            fn #ident(&self, req: &#req_type) -> ::bincode_grpc::grpcio::Result<#resp_type> {
-                self.#opt_method_ident(req, ::bincode_grpc::grpcio::CallOption::default())
+            fn #ident #generics(&self, #req_param) -> ::bincode_grpc::grpcio::Result<#resp_type> {
+                self.#opt_method_ident(#req_arg #default_opt)
             }
         }
     }
 
-    fn client_method_opt(&self, server_name: &Ident) -> TokenStream2 {
+    /// the `CallOption` of client calls made without one, with the timeout of `#[timeout(...)]`
+    /// or the one of the client
+    fn default_call_option(&self) -> TokenStream2 {
+        let timeout = match self.method_args.timeout_ms {
+            Some(ms) => quote::quote! { Some(::std::time::Duration::from_millis(#ms)) },
+            None => quote::quote! { None },
+        };
+        quote::quote! { self.default_call_option(#timeout) }
+    }
+
+    /// adds the headers of client calls to `opt`, the fingerprint with `#[service(fingerprint)]`
+    /// and the timeout of methods taking a `&CallInfo`
+    fn client_headers(&self, fingerprint: bool) -> TokenStream2 {
+        let mut headers = TokenStream2::new();
+        if fingerprint {
+            let fingerprint = self.fingerprint();
+            headers.extend(quote::quote! {
+                static FINGERPRINT: ::bincode_grpc::schema::CachedFingerprint = ::bincode_grpc::schema::CachedFingerprint::new();
+                let opt = FINGERPRINT.with_header(opt, || #fingerprint)?;
+            });
+        }
+        if self.call_info.is_some() {
+            headers.extend(quote::quote! {
+                let opt = ::bincode_grpc::call_info::with_timeout(opt)?;
+            });
+        }
+        headers
+    }
+
+    /// the fingerprint of the messages on the wire, without the errors of `#[status]` methods
+    fn fingerprint(&self) -> TokenStream2 {
+        let req_type = self.client_req_type();
+        let resp_type = match self.status_types() {
+            Some((ok, _)) => ok.into_token_stream(),
+            None => self.declared_resp_type(),
+        };
+        quote::quote! { ::bincode_grpc::schema::fingerprint::<#req_type, #resp_type>() }
+    }
+
+    fn client_method_opt(&self, server_name: &Ident, codec: &TokenStream2, fingerprint: bool) -> TokenStream2 {
         let ident = &self.ident;
-        let req_type = self.req_type();
-        let resp_type = self.resp_type();
+        let (req_param, _) = self.client_req_param();
+        let generics = self.client_generics();
+        let resp_type = self.client_resp_type();
         let opt_method_ident = quote::format_ident!("{}_opt", ident);
         let method_ident = self.method_declaration_ident(&server_name);
+        let headers = self.client_headers(fingerprint);
+        let payload = self.client_payload(codec);
+        let raw_method = quote::quote! { ::bincode_grpc::bi_codec::raw_request(&#method_ident) };
+        let (payload, call) = match self.kind() {
+            MethodKind::Unary => (
+                payload,
+                quote::quote! { ::bincode_grpc::interceptor::finish(call, self.client.unary_call(&#raw_method, &payload, opt)) },
+            ),
+            MethodKind::ClientStreaming => (
+                TokenStream2::new(),
+                quote::quote! {
+                    let (sender, receiver) = self.client.client_streaming(&#method_ident, opt)?;
+                    let sender = ::bincode_grpc::StreamSender::new(sender, ::bincode_grpc::bi_codec::check::<_, #codec>);
+                    Ok((sender, ::bincode_grpc::interceptor::Intercepted::new(receiver, call)))
+                },
+            ),
+            MethodKind::ServerStreaming => (
+                payload,
+                quote::quote! {
+                    let receiver = self.client.server_streaming(&#raw_method, &payload, opt)?;
+                    Ok(::bincode_grpc::interceptor::Intercepted::new(receiver, call))
+                },
+            ),
+            MethodKind::Duplex => (
+                TokenStream2::new(),
+                quote::quote! {
+                    let (sender, receiver) = self.client.duplex_streaming(&#method_ident, opt)?;
+                    let sender = ::bincode_grpc::StreamSender::new(sender, ::bincode_grpc::bi_codec::check::<_, #codec>);
+                    Ok((sender, ::bincode_grpc::interceptor::Intercepted::new(receiver, call)))
+                },
+            ),
+        };
+        let call = match self.status_types() {
+            Some(_) => quote::quote! { ::bincode_grpc::status::from_reply(#call) },
+            None => call,
+        };
+        let start = quote::quote! { let (opt, call) = self.interceptors.start(#method_ident.name, opt)?; };
+        // each attempt goes through the interceptors, with the headers of the time left
+        let (headers, start, call) = match &self.method_args.retry {
+            Some(RetryArgs { overrides }) => (
+                TokenStream2::new(),
+                TokenStream2::new(),
+                quote::quote! {
+                    let policy = self.retry_policy #( .#overrides )*;
+                    ::bincode_grpc::retry::retry(&policy, opt, |opt| {
+                        #headers
+                        #start
+                        #call
+                    })
+                },
+            ),
+            None => (headers, start, call),
+        };
 
         quote::quote! {
-            fn #opt_method_ident(&self, req: &#req_type, opt: ::bincode_grpc::grpcio::CallOption) -> ::bincode_grpc::grpcio::Result<#resp_type> {
-                self.client.unary_call(&#method_ident, req, opt)
+            fn #opt_method_ident #generics(&self, #req_param opt: ::bincode_grpc::grpcio::CallOption) -> ::bincode_grpc::grpcio::Result<#resp_type> {
+                #payload
+                #headers
+                #start
+                #call
             }
         }
     }
 
-    fn client_method_async_opt(&self, server_name: &Ident) -> TokenStream2 {
+    fn client_method_async_opt(&self, server_name: &Ident, codec: &TokenStream2, fingerprint: bool) -> TokenStream2 {
         let ident = &self.ident;
-        let req_type = self.req_type();
-        let resp_type = self.resp_type();
+        let (req_param, _) = self.client_req_param();
+        let generics = self.client_generics();
         let async_opt_method_ident = quote::format_ident!("{}_async_opt", ident);
         let method_ident = self.method_declaration_ident(&server_name);
+        let headers = self.client_headers(fingerprint);
+        let payload = self.client_payload(codec);
+        let future_type = self.client_future_type();
+        let future = quote::quote! { ::bincode_grpc::ClientFuture::new(receiver, call) };
+        let future = match self.status_types() {
+            Some(_) => quote::quote! { ::bincode_grpc::status::future(#future) },
+            None => future,
+        };
 
+        // errors raised before the call is sent are returned by the future
         quote::quote! {
-            fn #async_opt_method_ident(&self, req: &#req_type, opt: ::bincode_grpc::grpcio::CallOption) -> ::bincode_grpc::grpcio::Result<::grpcio::ClientUnaryReceiver<#resp_type>> {
-                self.client.unary_call_async(&#method_ident, req, opt)
+            fn #async_opt_method_ident #generics(&self, #req_param opt: ::bincode_grpc::grpcio::CallOption) -> #future_type {
+                let start = || -> ::bincode_grpc::grpcio::Result<#future_type> {
+                    #payload
+                    #headers
+                    let (opt, call) = self.interceptors.start(#method_ident.name, opt)?;
+                    let receiver = self.client.unary_call_async(&::bincode_grpc::bi_codec::raw_request(&#method_ident), &payload, opt)?;
+                    Ok(#future)
+                };
+                start().unwrap_or_else(::bincode_grpc::ClientFuture::failed)
             }
         }
     }
+
+    /// the future returned by asynchronous client methods
+    fn client_future_type(&self) -> TokenStream2 {
+        let resp_type = self.declared_resp_type();
+        quote::quote! { ::bincode_grpc::ClientFuture<#resp_type> }
+    }
+
     fn client_method_async(&self) -> TokenStream2 {
         let ident = &self.ident;
-        let req_type = self.req_type();
-        let resp_type = self.resp_type();
+        let (req_param, req_arg) = self.client_req_param();
+        let generics = self.client_generics();
+        let future_type = self.client_future_type();
         let async_method_ident = quote::format_ident!("{}_async", ident);
         let async_opt_method_ident = quote::format_ident!("{}_async_opt", ident);
 
+        let default_opt = self.default_call_option();
+
         quote::quote! {
-            fn #async_method_ident(&self, req: &#req_type) -> ::bincode_grpc::grpcio::Result<::grpcio::ClientUnaryReceiver<#resp_type>> {
-                self.#async_opt_method_ident(req, ::bincode_grpc::grpcio::CallOption::default())
+            fn #async_method_ident #generics(&self, #req_param) -> #future_type {
+                self.#async_opt_method_ident(#req_arg #default_opt)
             }
         }
     }
 
+    /// whether the method takes `&self`
+    fn shared(&self) -> bool {
+        self.receiver.mutability.is_none()
+    }
+
     /// transformed grpc compliant methods
     fn grpc_method(&self) -> TokenStream2 {
         let attrs = &self.attrs;
         let ident = &self.grpc_method_ident();
-        let receiver = &self.receiver;
+        // shared services are called through the `Arc` they are held in, which handlers outliving
+        // the call can clone
+        let receiver = if self.shared() {
+            quote::quote! { self: &::std::sync::Arc<Self> }
+        } else {
+            self.receiver.to_token_stream()
+        };
         let req_type = self.req_type();
-        let resp_type = self.resp_type();
+        let sink_type = self.sink_type();
 
         quote::quote! {
             #( #attrs )*
@@ -394,37 +1514,214 @@ impl RpcMethod {
                 #receiver,
                 ctx: ::bincode_grpc::grpcio::RpcContext,
                 req: #req_type,
-                sink: ::bincode_grpc::grpcio::UnarySink<#resp_type>
+                sink: #sink_type
               );
         }
     }
 
-    fn method_declaration(&self, service_name: &Ident) -> TokenStream2 {
+    fn method_declaration(&self, service_name: &Ident, codec: &TokenStream2, path: &str) -> TokenStream2 {
         let ident = self.method_declaration_ident(&service_name);
-        let req_type = self.req_type();
-        let resp_type = self.resp_type();
+        let req_type = self.client_req_type();
+        // borrowed structs cannot be decoded from a `MessageReader`, their requests are declared
+        // raw like the ones of servers and clients, see `raw_request`
+        let (req_type, req_ser, req_de) = if self.args.iter().any(|x| ArgKind::of(&x.ty) == ArgKind::Borrowed) {
+            (
+                quote::quote! { ::std::vec::Vec<u8> },
+                quote::quote! { ::bincode_grpc::bi_codec::raw_ser },
+                quote::quote! { ::bincode_grpc::bi_codec::raw_de },
+            )
+        } else {
+            (
+                req_type.clone(),
+                quote::quote! { ::bincode_grpc::bi_codec::ser::<#req_type, #codec> },
+                quote::quote! { ::bincode_grpc::bi_codec::de::<#req_type, #codec> },
+            )
+        };
+        let resp_type = self.declared_resp_type();
+        let method_type = match self.kind() {
+            MethodKind::Unary => quote::quote! { Unary },
+            MethodKind::ClientStreaming => quote::quote! { ClientStreaming },
+            MethodKind::ServerStreaming => quote::quote! { ServerStreaming },
+            MethodKind::Duplex => quote::quote! { Duplex },
+        };
+        let (resp_ser, resp_de) = match self.status_types() {
+            Some((ok, err)) => (
+                quote::quote! { ::bincode_grpc::bi_codec::ser_ok::<#ok, #err, #codec> },
+                quote::quote! { ::bincode_grpc::bi_codec::de_ok::<#ok, #err, #codec> },
+            ),
+            None => (
+                quote::quote! { ::bincode_grpc::bi_codec::ser::<#resp_type, #codec> },
+                quote::quote! { ::bincode_grpc::bi_codec::de::<#resp_type, #codec> },
+            ),
+        };
         quote::quote! {
             const #ident: ::bincode_grpc::grpcio::Method<#req_type, #resp_type> = ::bincode_grpc::grpcio::Method {
-                ty: ::bincode_grpc::grpcio::MethodType::Unary,
-                name: stringify!(#ident),
+                ty: ::bincode_grpc::grpcio::MethodType::#method_type,
+                name: #path,
                 req_mar: ::bincode_grpc::grpcio::Marshaller {
-                    ser: ::bincode_grpc::bi_codec::ser,
-                    de: ::bincode_grpc::bi_codec::de,
+                    ser: #req_ser,
+                    de: #req_de,
                 },
                 resp_mar: ::bincode_grpc::grpcio::Marshaller {
-                    ser: ::bincode_grpc::bi_codec::ser,
-                    de: ::bincode_grpc::bi_codec::de,
+                    ser: #resp_ser,
+                    de: #resp_de,
                 },
             };
         }
     }
 }
 
+/// Implements `::bincode_grpc::Schema` from the name, fields and variants of a type
+///
+/// ```ignore
+/// #[derive(Serialize, Deserialize, Schema)]
+/// pub struct HelloRequest {
+///     name: String,
+/// }
+/// ```
+#[proc_macro_derive(Schema)]
+pub fn derive_schema(tokens: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(tokens as syn::DeriveInput);
+    schema_impl(input).unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+fn schema_impl(mut input: syn::DeriveInput) -> syn::Result<TokenStream2> {
+    let ident = input.ident.clone();
+    let body = match &input.data {
+        syn::Data::Struct(data) => {
+            let fields = describe_fields(&data.fields);
+            let header = format!("struct {}", ident);
+            let f = if fields.is_empty() { quote::quote! { _ } } else { quote::quote! { f } };
+            quote::quote! {
+                f.named(::std::any::type_name::<Self>(), #header, |#f| {
+                    #fields
+                });
+            }
+        }
+        syn::Data::Enum(data) => {
+            let variants = data.variants.iter().filter(|v| !serde_skipped(&v.attrs)).map(|v| {
+                let name = format!("{}", v.ident);
+                let fields = describe_fields(&v.fields);
+                quote::quote! {
+                    f.write(#name);
+                    #fields
+                    f.write(",");
+                }
+            });
+            let header = format!("enum {}", ident);
+            quote::quote! {
+                f.named(::std::any::type_name::<Self>(), #header, |f| {
+                    f.write("{");
+                    #( #variants )*
+                    f.write("}");
+                });
+            }
+        }
+        syn::Data::Union(data) => return Err(syn::Error::new(data.union_token.span, "unions cannot derive `Schema`")),
+    };
+    for param in input.generics.type_params_mut() {
+        param.bounds.push(syn::parse_quote!(::bincode_grpc::schema::Schema));
+    }
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote::quote! {
+        impl #impl_generics ::bincode_grpc::schema::Schema for #ident #ty_generics #where_clause {
+            fn describe(f: &mut ::bincode_grpc::schema::Fingerprint) {
+                #body
+            }
+        }
+    })
+}
+
+/// describes `fields` as `{a:T,b:U,}`, `(T,U,)` or nothing for unit structs and variants
+fn describe_fields(fields: &syn::Fields) -> TokenStream2 {
+    let (open, close) = match fields {
+        syn::Fields::Named(_) => ("{", "}"),
+        syn::Fields::Unnamed(_) => ("(", ")"),
+        syn::Fields::Unit => return TokenStream2::new(),
+    };
+    let fields = fields.iter().filter(|field| !serde_skipped(&field.attrs)).map(|field| {
+        let ty = &field.ty;
+        let name = field.ident.as_ref().map(|ident| format!("{}:", ident)).unwrap_or_default();
+        quote::quote! {
+            f.write(#name);
+            <#ty as ::bincode_grpc::schema::Schema>::describe(f);
+            f.write(",");
+        }
+    });
+    quote::quote! {
+        f.write(#open);
+        #( #fields )*
+        f.write(#close);
+    }
+}
+
+/// whether serde leaves the field or variant out with `#[serde(skip)]`
+fn serde_skipped(attrs: &[Attribute]) -> bool {
+    attrs.iter().filter(|attr| attr.path.is_ident("serde")).any(|attr| match attr.parse_meta() {
+        Ok(syn::Meta::List(list)) => list.nested.iter().any(|meta| match meta {
+            syn::NestedMeta::Meta(syn::Meta::Path(path)) => path.is_ident("skip"),
+            _ => false,
+        }),
+        _ => false,
+    })
+}
+
 #[proc_macro_attribute]
-pub fn service(_attr: TokenStream, tokens: TokenStream) -> TokenStream {
-    syn::parse_macro_input!(tokens as Service)
-        .into_token_stream()
-        .into()
+pub fn service(attr: TokenStream, tokens: TokenStream) -> TokenStream {
+    let args = syn::parse_macro_input!(attr as ServiceArgs);
+    let mut service = syn::parse_macro_input!(tokens as Service);
+    service.args = args;
+    service.into_token_stream().into()
+}
+
+/// Options of the `#[server(...)]` attribute
+///
+/// ```
+/// #[server(blocking_pool = 4, streaming_pool = 16)]
+/// ```
+///
+/// `blocking_pool = N` runs all methods of the impl on a pool of `N` threads instead of the
+/// completion queue threads of grpcio, `#[blocking]` does it for a single method with a thread
+/// per CPU. Handlers run on a clone of the `Arc` holding the server after the `*_grpc` call
+/// returns, so these methods must take `&self`.
+///
+/// Client streaming methods running on a pool, which synchronous ones always do as they block on
+/// the requests, run on a separate pool of `streaming_pool = N` threads, one per CPU by default.
+/// Each upload holds a thread until the client closes its stream and the following ones wait for
+/// a free thread, without starving the other methods. Make the method `async` to await the
+/// requests on the completion queue instead.
+#[derive(Default)]
+struct ServerArgs {
+    blocking_pool: Option<syn::LitInt>,
+    streaming_pool: Option<syn::LitInt>,
+}
+
+impl Parse for ServerArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut args = Self::default();
+        while !input.is_empty() {
+            let key: Ident = input.parse()?;
+            match key.to_string().as_str() {
+                "blocking_pool" | "streaming_pool" => {
+                    input.parse::<token::Eq>()?;
+                    let size: syn::LitInt = input.parse()?;
+                    if size.base10_parse::<usize>()? == 0 {
+                        return Err(syn::Error::new(size.span(), format!("the {} pool needs at least one thread", key.to_string().trim_end_matches("_pool"))));
+                    }
+                    if key == "blocking_pool" {
+                        args.blocking_pool = Some(size);
+                    } else {
+                        args.streaming_pool = Some(size);
+                    }
+                }
+                _ => return Err(syn::Error::new(key.span(), format!("unknown server option `{}`", key))),
+            }
+            if !input.is_empty() {
+                input.parse::<token::Comma>()?;
+            }
+        }
+        Ok(args)
+    }
 }
 
 /// ```
@@ -446,7 +1743,7 @@ pub fn service(_attr: TokenStream, tokens: TokenStream) -> TokenStream {
 ///         HelloReply::default()
 ///     }
 ///
-///     fn say_hello_grpc(&mut self, ctx: RpcContext<'_>, req: (HelloRequest,), sink: UnarySink<HelloReply>) {
+///     fn say_hello_grpc(&mut self, ctx: RpcContext<'_>, req: (HelloRequest,), sink: ::bincode_grpc::UnarySink<HelloReply>) {
 ///         let mut resp = self.say_hello(req);
 ///         let f = sink
 ///             .success(resp)
@@ -456,10 +1753,43 @@ pub fn service(_attr: TokenStream, tokens: TokenStream) -> TokenStream {
 ///     }
 /// }
 /// ```
+///
+/// `async fn` methods are awaited on the completion queue of the call, so they can await other RPCs,
+/// timers and IO. They outlive the `*_grpc` call and run on a clone of the `Arc` holding the
+/// server, so they must take `&self`.
 #[proc_macro_attribute]
-pub fn server(_attr: TokenStream, tokens: TokenStream) -> TokenStream {
+pub fn server(attr: TokenStream, tokens: TokenStream) -> TokenStream {
+    let args = syn::parse_macro_input!(attr as ServerArgs);
     let mut item = syn::parse_macro_input!(tokens as syn::ItemImpl);
-    let new_methods: Vec<_> = item
+    let trait_ident = match &item.trait_ {
+        Some((_, path, _)) => path.segments.last().map(|s| s.ident.clone()),
+        None => None,
+    };
+    let trait_ident = match trait_ident {
+        Some(ident) => ident,
+        None => return syn::Error::new_spanned(&item.self_ty, "`#[server]` expects an impl of a `#[service]` trait").to_compile_error().into(),
+    };
+    let pool_ident = quote::format_ident!("{}_blocking_pool", trait_ident.to_string().to_snake_case());
+    let streaming_pool_ident = quote::format_ident!("{}_streaming_pool", trait_ident.to_string().to_snake_case());
+
+    let mut blocking = vec![];
+    for item in item.items.iter_mut() {
+        if let ImplItem::Method(m) = item {
+            if let Err(e) = check_server_method(m, args.blocking_pool.is_some()) {
+                return e.to_compile_error().into();
+            }
+            let len = m.attrs.len();
+            m.attrs.retain(|attr| !attr.path.is_ident("blocking"));
+            // synchronous client streaming methods block on the requests, which only arrive once
+            // the completion queue thread is released
+            let blocks_on_requests = m.sig.asyncness.is_none() && is_client_streaming(&m.sig);
+            if args.blocking_pool.is_some() || m.attrs.len() != len || blocks_on_requests {
+                blocking.push((m.sig.ident.clone(), is_client_streaming(&m.sig)));
+            }
+        }
+    }
+
+    let new_methods: syn::Result<Vec<_>> = item
         .items
         .iter()
         .filter_map(|item| match item {
@@ -474,8 +1804,8 @@ pub fn server(_attr: TokenStream, tokens: TokenStream) -> TokenStream {
             let req_type = {
                 let args = &m.sig.inputs;
                 let all_arg_types: Vec<_> = args.iter().filter_map(|x| match x {
-                    FnArg::Receiver(_) => None,
-                    FnArg::Typed(x) => Some(x.ty.clone()),
+                    FnArg::Typed(x) if !is_call_info(&x.ty) => Some(x.ty.clone()),
+                    _ => None,
                 }).collect();
                 if all_arg_types.len() > 0 {
                     quote::quote! {
@@ -491,65 +1821,171 @@ pub fn server(_attr: TokenStream, tokens: TokenStream) -> TokenStream {
             let req_args: Vec<_> = {
                 let args = &m.sig.inputs;
                 let all_arg_types = args.iter().filter_map(|x| match x {
-                    FnArg::Receiver(_) => None,
-                    FnArg::Typed(x) => Some(x.pat.clone()),
+                    FnArg::Typed(x) if !is_call_info(&x.ty) => Some(x.pat.clone()),
+                    _ => None,
                 });
                 all_arg_types
             }.collect();
 
-            let req_args2 = req_args.clone();
+            // arguments of the call, with the `&CallInfo` built from the context in place
+            let req_args2: Vec<_> = m.sig.inputs.iter().filter_map(|x| match x {
+                FnArg::Typed(x) if is_call_info(&x.ty) => Some(quote::quote! { &call_info }),
+                FnArg::Typed(x) => Some(x.pat.to_token_stream()),
+                FnArg::Receiver(_) => None,
+            }).collect();
+            let has_call_info = m.sig.inputs.iter().any(|x| matches!(x, FnArg::Typed(x) if is_call_info(&x.ty)));
+            // see `RpcMethod::grpc_method`
+            let (receiver, service) = match m.sig.receiver() {
+                Some(FnArg::Receiver(r)) if r.mutability.is_none() => (quote::quote! { self: &::std::sync::Arc<Self> }, quote::quote! { service }),
+                _ => (quote::quote! { &mut self }, quote::quote! { mut service }),
+            };
+            let call_info = if has_call_info {
+                quote::quote! { let call_info = ::bincode_grpc::CallInfo::new(&ctx); }
+            } else {
+                TokenStream2::new()
+            };
+
+            let resp_type = match &m.sig.output {
+                ReturnType::Default => syn::Type::Verbatim(quote::quote! {()}),
+                ReturnType::Type(_, t) => (**t).clone(),
+            };
 
-            let resp_type = {
-                let output = &m.sig.output;
-                let output = match output {
-                    ReturnType::Default => syn::Type::Verbatim(quote::quote! {()}),
-                    ReturnType::Type(_, t) => (**t).clone(),
-                };
-                quote::quote! {
-                    #output
-                }
+            let (sink_type, reply) = match wrapped_type(&resp_type, "ServerStream") {
+                Some(item) => (
+                    quote::quote! { ::bincode_grpc::ServerStreamingSink<#item> },
+                    quote::quote! { sink.send_stream(&ctx, resp) },
+                ),
+                None => (
+                    quote::quote! { ::bincode_grpc::UnarySink<#resp_type> },
+                    quote::quote! {
+                        let f = sink
+                            .success(resp)
+                            .map_err(move |e| ::bincode_grpc::tracing::error!("failed to reply {:?}", e))
+                            .map(|_| ());
+                        ctx.spawn(f)
+                    },
+                ),
             };
 
-            if req_args.len() > 0 {
+            let send = match wrapped_type(&resp_type, "ServerStream") {
+                Some(_) => quote::quote! { sink.send_all(resp) },
+                None => quote::quote! { sink.success(resp) },
+            };
+
+            let call = match m.sig.asyncness {
+                Some(_) => quote::quote! { service.#method_ident(#( #req_args2, )* ).await },
+                None => quote::quote! { service.#method_ident(#( #req_args2, )* ) },
+            };
+            // pool threads are outside of any executor, so they block on async methods and replies
+            let blocking_call = match m.sig.asyncness {
+                Some(_) => quote::quote! { ::bincode_grpc::futures::executor::block_on(service.#method_ident(#( #req_args2, )* )) },
+                None => call.clone(),
+            };
+
+            let pool = blocking.iter().find(|(ident, _)| ident == method_ident).map(|(_, streaming)| {
+                if *streaming { &streaming_pool_ident } else { &pool_ident }
+            });
+            Ok(if let Some(pool_ident) = pool {
+                let borrowed = m.sig.inputs.iter().find(|x| match x {
+                    FnArg::Typed(x) => ArgKind::of(&x.ty).borrows(),
+                    FnArg::Receiver(_) => false,
+                });
+                if let Some(arg) = borrowed {
+                    return Err(syn::Error::new_spanned(arg, "blocking methods outlive the request and cannot borrow from it, use `bytes::Bytes` instead"));
+                }
+                // a pool which cannot start fails the call instead of panicking on the completion queue
                 quote::quote! {
-                    #vis fn #grpc_method_ident(&mut self, ctx: ::bincode_grpc::grpcio::RpcContext, req: #req_type, sink: ::bincode_grpc::grpcio::UnarySink<#resp_type>) {
+                    #vis fn #grpc_method_ident(#receiver, ctx: ::bincode_grpc::grpcio::RpcContext, req: #req_type, sink: #sink_type) {
+                         let pool = match Self::#pool_ident().start() {
+                             Ok(pool) => pool,
+                             Err(status) => return ::bincode_grpc::handler::reject(&ctx, sink, status),
+                         };
                          let (#( #req_args, )*) = req;
+                         #call_info
+                         let #service = self.clone();
+                         pool.spawn(move || {
+                             let resp = #blocking_call;
+                             if let Err(e) = ::bincode_grpc::futures::executor::block_on(#send) {
+                                 ::bincode_grpc::tracing::error!("failed to reply {:?}", e)
+                             }
+                         });
+                    }
+                }
+            } else if m.sig.asyncness.is_some() {
+                // the future outlives this call, so it runs on a clone of the `Arc` of the service
+                quote::quote! {
+                    #vis fn #grpc_method_ident(#receiver, ctx: ::bincode_grpc::grpcio::RpcContext, req: #req_type, sink: #sink_type) {
+                         let (#( #req_args, )*) = req;
+                         #call_info
+                         let #service = self.clone();
+                         ctx.spawn(async move {
+                             let resp = #call;
+                             if let Err(e) = #send.await {
+                                 ::bincode_grpc::tracing::error!("failed to reply {:?}", e)
+                             }
+                         });
+                    }
+                }
+            } else if req_args.len() > 0 {
+                quote::quote! {
+                    #vis fn #grpc_method_ident(#receiver, ctx: ::bincode_grpc::grpcio::RpcContext, req: #req_type, sink: #sink_type) {
+                         let (#( #req_args, )*) = req;
+                         #call_info
                          let mut resp = self.#method_ident(#( #req_args2, )* );
-                         let f = sink
-                             .success(resp)
-                             .map_err(move |e| ::bincode_grpc::tracing::error!("failed to reply {:?}", e))
-                             .map(|_| ());
-                         ctx.spawn(f)
+                         #reply
                     }
                 }
             } else {
                 quote::quote! {
-                    #vis fn #grpc_method_ident(&mut self, ctx: ::bincode_grpc::grpcio::RpcContext, _req: #req_type, sink: ::bincode_grpc::grpcio::UnarySink<#resp_type>) {
-                         let mut resp = self.#method_ident();
-                         let f = sink
-                             .success(resp)
-                             .map_err(move |e| ::bincode_grpc::tracing::error!("failed to reply {:?}", e))
-                             .map(|_| ());
-                         ctx.spawn(f)
+                    #vis fn #grpc_method_ident(#receiver, ctx: ::bincode_grpc::grpcio::RpcContext, _req: #req_type, sink: #sink_type) {
+                         #call_info
+                         let mut resp = self.#method_ident(#( #req_args2, )* );
+                         #reply
                     }
                 }
-            }
+            })
         })
         .collect();
+    let new_methods = match new_methods {
+        Ok(methods) => methods,
+        Err(e) => return e.to_compile_error().into(),
+    };
 
     let original_items = std::mem::replace(&mut item.items, vec![]);
 
+    // uploads hold their thread for as long as the client streams, so they get their own pool
+    let pool_fn = |ident: &Ident, size: &Option<syn::LitInt>, used: bool| {
+        if !used {
+            return TokenStream2::new();
+        }
+        let init = match size {
+            Some(size) => quote::quote! { ::bincode_grpc::BlockingPool::with_size(#size) },
+            None => quote::quote! { ::bincode_grpc::BlockingPool::new() },
+        };
+        quote::quote! {
+            fn #ident() -> &'static ::bincode_grpc::BlockingPool {
+                static POOL: ::bincode_grpc::BlockingPool = #init;
+                &POOL
+            }
+        }
+    };
+    let streaming_pool = pool_fn(&streaming_pool_ident, &args.streaming_pool, blocking.iter().any(|(_, streaming)| *streaming));
+    let pool = pool_fn(&pool_ident, &args.blocking_pool, blocking.iter().any(|(_, streaming)| !*streaming));
+
     let impl_ident = item.self_ty.clone();
     let original_impl = quote::quote! {
         impl #impl_ident {
             #( #original_items )*
+            #pool
+            #streaming_pool
         }
     };
 
     for method in new_methods {
-        let method: syn::ImplItemMethod =
-            syn::parse(method.into_token_stream().into()).expect("cannot parse method");
-        item.items.push(syn::ImplItem::Method(method));
+        match syn::parse2::<syn::ImplItemMethod>(method) {
+            Ok(method) => item.items.push(syn::ImplItem::Method(method)),
+            Err(e) => return e.to_compile_error().into(),
+        }
     }
 
     let new_item = item.into_token_stream();
@@ -559,3 +1995,74 @@ pub fn server(_attr: TokenStream, tokens: TokenStream) -> TokenStream {
         #new_item
     }).into()
 }
+
+/// rejects the methods of a `#[server]` impl which cannot be called by the generated shims,
+/// mirroring the checks of `#[service]` on the trait
+fn check_server_method(m: &syn::ImplItemMethod, blocking_pool: bool) -> syn::Result<()> {
+    let sig = &m.sig;
+    if let Some(constness) = &sig.constness {
+        return Err(syn::Error::new_spanned(constness, "RPC methods cannot be `const`"));
+    }
+    if let Some(unsafety) = &sig.unsafety {
+        return Err(syn::Error::new_spanned(unsafety, "RPC methods cannot be `unsafe`"));
+    }
+    if let Some(abi) = &sig.abi {
+        return Err(syn::Error::new_spanned(abi, "RPC methods cannot have an `extern` ABI"));
+    }
+    if !sig.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(&sig.generics, "RPC methods cannot be generic, the types of the messages must be known"));
+    }
+    if let Some(where_clause) = &sig.generics.where_clause {
+        return Err(syn::Error::new_spanned(where_clause, "RPC methods cannot have a `where` clause"));
+    }
+    match sig.receiver() {
+        Some(FnArg::Receiver(receiver)) if receiver.reference.is_none() => {
+            return Err(syn::Error::new_spanned(receiver, "RPC methods must take `&self` or `&mut self`, not `self`"));
+        }
+        Some(FnArg::Receiver(_)) => {}
+        Some(receiver) => return Err(syn::Error::new_spanned(receiver, "RPC methods must take `&self` or `&mut self`")),
+        None => return Err(syn::Error::new(sig.paren_token.span, "RPC methods must take `&self` or `&mut self`")),
+    }
+    for arg in &sig.inputs {
+        if let FnArg::Typed(arg) = arg {
+            if !matches!(&*arg.pat, syn::Pat::Ident(_)) {
+                return Err(syn::Error::new_spanned(&arg.pat, "patterns are not supported in RPC arguments, bind the argument to a name"));
+            }
+        }
+    }
+    if let (Some(_), Some(FnArg::Receiver(receiver))) = (&sig.asyncness, sig.receiver()) {
+        if receiver.mutability.is_some() {
+            return Err(mut_self_error(receiver, "`async` methods"));
+        }
+    }
+    if let Some(attr) = m.attrs.iter().find(|attr| attr.path.is_ident("blocking") && !attr.tokens.is_empty()) {
+        return Err(syn::Error::new_spanned(&attr.tokens, "`#[blocking]` takes no arguments, set the size of the pool with `#[server(blocking_pool = N)]`"));
+    }
+    if let Some(FnArg::Receiver(receiver)) = sig.receiver() {
+        if receiver.mutability.is_some() {
+            if is_client_streaming(sig) {
+                return Err(mut_self_error(receiver, "client streaming methods"));
+            }
+            if blocking_pool || m.attrs.iter().any(|attr| attr.path.is_ident("blocking")) {
+                return Err(mut_self_error(receiver, "blocking methods"));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// whether the method of a `#[server]` impl takes a `ClientStream<T>`
+fn is_client_streaming(sig: &syn::Signature) -> bool {
+    sig.inputs.iter().any(|x| match x {
+        FnArg::Typed(x) => wrapped_type(&x.ty, "ClientStream").is_some(),
+        FnArg::Receiver(_) => false,
+    })
+}
+
+/// the error of `&mut self` methods whose calls outlive the handler, `methods` describing them
+fn mut_self_error(receiver: impl ToTokens, methods: &str) -> syn::Error {
+    syn::Error::new_spanned(
+        receiver,
+        format!("{} outlive the call and would mutate a clone of the service, take `&self` and keep the state behind an `Arc`", methods),
+    )
+}
@@ -0,0 +1,8 @@
+//! Diagnostics of the macros for invalid services and servers, run with `TRYBUILD=overwrite` to
+//! update the expected messages.
+
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
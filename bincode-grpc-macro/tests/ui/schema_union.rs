@@ -0,0 +1,7 @@
+#[derive(bincode_grpc_macro::Schema)]
+union Word {
+    int: u32,
+    float: f32,
+}
+
+fn main() {}
@@ -0,0 +1,21 @@
+#![allow(dead_code)]
+
+struct Chunk<'a> {
+    data: &'a [u8],
+}
+
+trait Archive {
+    fn store(&self, chunk: Chunk<'_>) -> u64;
+}
+
+struct ArchiveServer;
+
+#[bincode_grpc_macro::server]
+impl Archive for ArchiveServer {
+    #[blocking]
+    fn store(&self, chunk: Chunk<'_>) -> u64 {
+        chunk.data.len() as u64
+    }
+}
+
+fn main() {}
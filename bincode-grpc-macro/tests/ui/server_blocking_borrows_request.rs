@@ -0,0 +1,17 @@
+#![allow(dead_code)]
+
+trait Greeter {
+    fn say_hello(&self, name: &str) -> String;
+}
+
+struct GreeterServer;
+
+#[bincode_grpc_macro::server]
+impl Greeter for GreeterServer {
+    #[blocking]
+    fn say_hello(&self, name: &str) -> String {
+        name.to_owned()
+    }
+}
+
+fn main() {}
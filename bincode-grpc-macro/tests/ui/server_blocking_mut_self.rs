@@ -0,0 +1,17 @@
+#![allow(dead_code)]
+
+trait Greeter {
+    fn say_hello(&mut self, name: String) -> String;
+}
+
+struct GreeterServer;
+
+#[bincode_grpc_macro::server]
+impl Greeter for GreeterServer {
+    #[blocking]
+    fn say_hello(&mut self, name: String) -> String {
+        name
+    }
+}
+
+fn main() {}
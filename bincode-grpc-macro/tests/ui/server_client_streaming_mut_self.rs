@@ -0,0 +1,18 @@
+#![allow(dead_code)]
+
+struct ClientStream<T>(Vec<T>);
+
+trait Uploader {
+    fn upload(&mut self, chunks: ClientStream<Vec<u8>>) -> u64;
+}
+
+struct UploadServer;
+
+#[bincode_grpc_macro::server]
+impl Uploader for UploadServer {
+    fn upload(&mut self, chunks: ClientStream<Vec<u8>>) -> u64 {
+        chunks.0.len() as u64
+    }
+}
+
+fn main() {}
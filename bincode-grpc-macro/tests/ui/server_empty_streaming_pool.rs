@@ -0,0 +1,16 @@
+#![allow(dead_code)]
+
+trait Greeter {
+    fn say_hello(&self, name: String) -> String;
+}
+
+struct GreeterServer;
+
+#[bincode_grpc_macro::server(streaming_pool = 0)]
+impl Greeter for GreeterServer {
+    fn say_hello(&self, name: String) -> String {
+        name
+    }
+}
+
+fn main() {}
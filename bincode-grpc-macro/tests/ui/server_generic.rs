@@ -0,0 +1,16 @@
+#![allow(dead_code)]
+
+trait Greeter {
+    fn say_hello<T: ToString>(&mut self, name: T) -> String;
+}
+
+struct GreeterServer;
+
+#[bincode_grpc_macro::server]
+impl Greeter for GreeterServer {
+    fn say_hello<T: ToString>(&mut self, name: T) -> String {
+        name.to_string()
+    }
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+#![allow(dead_code)]
+
+struct GreeterServer;
+
+#[bincode_grpc_macro::server]
+impl GreeterServer {
+    fn say_hello(&mut self, name: String) -> String {
+        name
+    }
+}
+
+fn main() {}
@@ -0,0 +1,16 @@
+#![allow(dead_code)]
+
+trait Greeter {
+    fn say_hello(&mut self, name: (String, String)) -> String;
+}
+
+struct GreeterServer;
+
+#[bincode_grpc_macro::server]
+impl Greeter for GreeterServer {
+    fn say_hello(&mut self, (first, last): (String, String)) -> String {
+        first + &last
+    }
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+struct Chunk<'a> {
+    data: &'a [u8],
+}
+
+#[bincode_grpc_macro::service]
+trait Archive {
+    async fn store(&self, chunk: Chunk<'_>) -> u64;
+}
+
+fn main() {}
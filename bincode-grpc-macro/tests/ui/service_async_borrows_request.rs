@@ -0,0 +1,6 @@
+#[bincode_grpc_macro::service]
+trait Greeter {
+    async fn say_hello(&self, name: &str) -> String;
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+#[bincode_grpc_macro::service]
+trait Greeter {
+    fn say_hello(&mut self, names: ClientStream<String>, greeting: String) -> String;
+}
+
+fn main() {}
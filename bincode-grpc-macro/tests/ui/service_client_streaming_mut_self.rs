@@ -0,0 +1,6 @@
+#[bincode_grpc_macro::service]
+trait Uploader {
+    fn upload(&mut self, chunks: ClientStream<Vec<u8>>) -> u64;
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+#[bincode_grpc_macro::service]
+trait Archive {
+    #[compress(zstd)]
+    fn store(&self, name: &str, data: Vec<u8>) -> u64;
+}
+
+fn main() {}
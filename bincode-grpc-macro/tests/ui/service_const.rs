@@ -0,0 +1,6 @@
+#[bincode_grpc_macro::service]
+trait Greeter {
+    const fn say_hello(&mut self, name: String) -> String;
+}
+
+fn main() {}
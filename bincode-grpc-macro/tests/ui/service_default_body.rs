@@ -0,0 +1,8 @@
+#[bincode_grpc_macro::service]
+trait Greeter {
+    fn say_hello(&mut self, name: String) -> String {
+        name
+    }
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+#[bincode_grpc_macro::service]
+trait Greeter {
+    fn say_hello(&mut self, info: &CallInfo, again: &CallInfo, name: String) -> String;
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+#[bincode_grpc_macro::service]
+trait Greeter {
+    fn say_hello<T>(&mut self, name: T) -> String;
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+#[bincode_grpc_macro::service]
+trait Greeter {
+    fn say_hello(&self, name: String) -> String;
+    fn say_goodbye(&mut self, name: String) -> String;
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+#[bincode_grpc_macro::service]
+trait Greeter {
+    #[retry(attempts = 3)]
+    fn say_hello(&mut self, name: String) -> String;
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+#[bincode_grpc_macro::service]
+trait Greeter {
+    #[status]
+    fn say_hello(&mut self, names: ClientStream<String>) -> Result<String, String>;
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+#[bincode_grpc_macro::service]
+trait Greeter {
+    fn say_hello(self: Box<Self>, name: String) -> String;
+}
+
+fn main() {}
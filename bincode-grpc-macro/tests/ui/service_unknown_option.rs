@@ -0,0 +1,6 @@
+#[bincode_grpc_macro::service(compression)]
+trait Greeter {
+    fn say_hello(&mut self, name: String) -> String;
+}
+
+fn main() {}
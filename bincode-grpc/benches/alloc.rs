@@ -0,0 +1,120 @@
+//! Allocations of the marshallers, compared with the former ones which swapped a freshly
+//! serialized `Vec` into the output and read every message into its own buffer. Run with
+//! `cargo bench --bench alloc -- --nocapture` to see them.
+//!
+//! `de` is the path of clients decoding responses, `request` and `reply` the ones of servers
+//! reading raw requests and checking then serializing responses.
+#![feature(test)]
+extern crate test;
+
+use bincode_grpc::bi_codec::{self, Bincode};
+use serde::{Deserialize, Serialize};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io::Read;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use test::Bencher;
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+#[derive(Serialize, Deserialize)]
+struct Message {
+    id: u64,
+    tags: [u32; 16],
+    score: f64,
+}
+
+const MESSAGE: Message = Message { id: 42, tags: [7; 16], score: 0.5 };
+
+/// Runs `f` in the benchmark, printing the allocations it makes per call.
+fn bench_allocations(b: &mut Bencher, name: &str, mut f: impl FnMut()) {
+    f();
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    const CALLS: usize = 1000;
+    for _ in 0..CALLS {
+        f();
+    }
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    eprintln!("{}: {:.2} allocations per call", name, allocations as f64 / CALLS as f64);
+    b.iter(f);
+}
+
+#[bench]
+fn ser_fresh_vec(b: &mut Bencher) {
+    let mut buf = Vec::new();
+    bench_allocations(b, "ser_fresh_vec", || {
+        buf = Vec::new();
+        let serialized = bincode::serialize(&MESSAGE).unwrap();
+        assert_eq!(std::mem::replace(&mut buf, serialized).len(), 0);
+        test::black_box(&buf);
+    });
+}
+
+#[bench]
+fn ser(b: &mut Bencher) {
+    let mut buf = Vec::new();
+    bench_allocations(b, "ser", || {
+        buf = Vec::new();
+        bi_codec::ser::<_, Bincode>(&MESSAGE, &mut buf);
+        test::black_box(&buf);
+    });
+}
+
+#[bench]
+fn de_fresh_vec(b: &mut Bencher) {
+    let payload = bincode::serialize(&MESSAGE).unwrap();
+    bench_allocations(b, "de_fresh_vec", || {
+        let mut buf = Vec::with_capacity(payload.len());
+        (&payload[..]).read_to_end(&mut buf).unwrap();
+        test::black_box(bi_codec::decode::<Message, Bincode>(&buf, None).unwrap());
+    });
+}
+
+#[bench]
+fn de(b: &mut Bencher) {
+    let payload = bincode::serialize(&MESSAGE).unwrap();
+    bench_allocations(b, "de", || {
+        test::black_box(bi_codec::de_read::<Message, Bincode>(&payload[..], payload.len()).unwrap());
+    });
+}
+
+#[bench]
+fn request(b: &mut Bencher) {
+    let payload = bincode::serialize(&(MESSAGE,)).unwrap();
+    bench_allocations(b, "request", || {
+        let req = bi_codec::read_raw(&payload[..], payload.len()).unwrap();
+        test::black_box(bi_codec::decode::<(Message,), Bincode>(&req, None).unwrap());
+        bi_codec::recycle(req);
+    });
+}
+
+#[bench]
+fn reply(b: &mut Bencher) {
+    let mut buf = Vec::new();
+    bench_allocations(b, "reply", || {
+        buf = Vec::new();
+        bi_codec::check::<_, Bincode>(&MESSAGE).unwrap();
+        bi_codec::ser::<_, Bincode>(&MESSAGE, &mut buf);
+        test::black_box(&buf);
+    });
+}
@@ -0,0 +1,45 @@
+//! Decoding a large binary request into owned and borrowed arguments.
+#![feature(test)]
+extern crate test;
+
+use bincode_grpc::bi_codec::{self, Bincode};
+use bincode_grpc::bytes::Bytes;
+use test::Bencher;
+
+const BLOB_SIZE: usize = 1 << 20;
+
+fn request() -> Vec<u8> {
+    let mut buf = vec![];
+    bi_codec::ser::<_, Bincode>(&("blob".to_string(), vec![7u8; BLOB_SIZE]), &mut buf);
+    buf
+}
+
+#[bench]
+fn decode_owned(b: &mut Bencher) {
+    let payload = request();
+    b.bytes = payload.len() as u64;
+    b.iter(|| {
+        let (name, blob) = bi_codec::decode::<(String, Vec<u8>), Bincode>(&payload, None).unwrap();
+        test::black_box((name, blob));
+    });
+}
+
+#[bench]
+fn decode_borrowed(b: &mut Bencher) {
+    let payload = request();
+    b.bytes = payload.len() as u64;
+    b.iter(|| {
+        let (name, blob) = bi_codec::decode_borrowed::<(&str, &[u8]), Bincode>(&payload, None).unwrap();
+        test::black_box((name, blob));
+    });
+}
+
+#[bench]
+fn decode_bytes(b: &mut Bencher) {
+    let payload = Bytes::from(request());
+    b.bytes = payload.len() as u64;
+    b.iter(|| {
+        let (name, blob) = bi_codec::decode_borrowed::<(&str, &[u8]), Bincode>(&payload, None).unwrap();
+        test::black_box((name, payload.slice_ref(blob)));
+    });
+}
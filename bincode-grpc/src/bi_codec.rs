@@ -0,0 +1,341 @@
+use bincode::config::{AllowTrailing, FixintEncoding, LittleEndian, WithOtherEndian, WithOtherIntEncoding, WithOtherTrailing};
+use bincode::{DefaultOptions, Options};
+use grpcio::{Error, Marshaller, MessageReader, Method, Result, RpcStatus, RpcStatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::fmt;
+use std::io::Read;
+use std::marker::PhantomData;
+use std::time::Instant;
+
+mod compression;
+
+pub use compression::*;
+
+/// The serialization format of a service's messages.
+///
+/// Codecs are stateless and used as a type parameter of the marshallers, so the generated
+/// `*_METHOD_*` declarations stay plain function pointers. Services use [`Bincode`] unless
+/// another codec is chosen with `#[service(codec = MyCodec)]`.
+pub trait Codec {
+    /// Appends the serialized `msg` to `buf`.
+    fn encode<M: Serialize>(msg: &M, buf: &mut Vec<u8>) -> std::result::Result<(), CodecError>;
+
+    /// Deserializes a message from `buf`.
+    ///
+    /// Buffers longer than `limit` are rejected before reaching the codec, codecs should still
+    /// refuse to allocate for more than `limit` bytes on behalf of a length prefix.
+    fn decode<M: DeserializeOwned>(buf: &[u8], limit: Option<u64>) -> std::result::Result<M, CodecError>;
+
+    /// Checks that `msg` can be encoded, codecs able to do so without producing the output
+    /// should override it.
+    fn check<M: Serialize>(msg: &M) -> std::result::Result<(), CodecError> {
+        Self::encode(msg, &mut Vec::new())
+    }
+
+    /// The encoded size of `msg`, used by [`ser`] to reserve the output buffer at once.
+    fn size_hint<M: Serialize>(_msg: &M) -> Option<usize> {
+        None
+    }
+}
+
+/// A [`Codec`] able to deserialize messages borrowing from the received buffer.
+///
+/// Required by methods taking `&str`, `&[u8]` or `bytes::Bytes` arguments, which are then
+/// handed to the server without being copied out of the request.
+pub trait BorrowCodec: Codec {
+    /// Deserializes a message borrowing from `buf`, with the same limits as [`Codec::decode`].
+    fn decode_borrowed<'de, M: Deserialize<'de>>(buf: &'de [u8], limit: Option<u64>) -> std::result::Result<M, CodecError>;
+}
+
+/// Error of a [`Codec`].
+#[derive(Debug)]
+pub enum CodecError {
+    /// The message is larger than the decode limit.
+    LimitExceeded,
+    /// The message cannot be encoded or decoded.
+    Invalid(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodecError::LimitExceeded => write!(f, "message exceeds the decode limit"),
+            CodecError::Invalid(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CodecError::LimitExceeded => None,
+            CodecError::Invalid(e) => Some(e.as_ref()),
+        }
+    }
+}
+
+impl From<bincode::Error> for CodecError {
+    fn from(e: bincode::Error) -> Self {
+        match *e {
+            bincode::ErrorKind::SizeLimit => CodecError::LimitExceeded,
+            _ => CodecError::Invalid(e),
+        }
+    }
+}
+
+impl From<std::io::Error> for CodecError {
+    fn from(e: std::io::Error) -> Self {
+        CodecError::Invalid(Box::new(e))
+    }
+}
+
+/// The bincode configuration used by a service's marshallers.
+///
+/// `#[service(codec(...))]` generates an implementation for the service, services without the
+/// option use [`LegacyConfig`].
+pub trait BincodeConfig {
+    type Options: Options;
+
+    fn options() -> Self::Options;
+}
+
+/// The configuration of `bincode::serialize` and `bincode::deserialize`: fixint encoding,
+/// little endian and trailing bytes allowed.
+pub struct LegacyConfig;
+
+impl BincodeConfig for LegacyConfig {
+    type Options = WithOtherTrailing<WithOtherEndian<WithOtherIntEncoding<DefaultOptions, FixintEncoding>, LittleEndian>, AllowTrailing>;
+
+    fn options() -> Self::Options {
+        DefaultOptions::new()
+            .with_fixint_encoding()
+            .with_little_endian()
+            .allow_trailing_bytes()
+    }
+}
+
+/// The default codec: bincode configured by `O`.
+pub struct Bincode<O = LegacyConfig>(PhantomData<O>);
+
+impl<O: BincodeConfig> Codec for Bincode<O> {
+    fn encode<M: Serialize>(msg: &M, buf: &mut Vec<u8>) -> std::result::Result<(), CodecError> {
+        Ok(O::options().serialize_into(buf, msg)?)
+    }
+
+    fn decode<M: DeserializeOwned>(buf: &[u8], limit: Option<u64>) -> std::result::Result<M, CodecError> {
+        Self::decode_borrowed(buf, limit)
+    }
+
+    fn check<M: Serialize>(msg: &M) -> std::result::Result<(), CodecError> {
+        O::options().serialized_size(msg)?;
+        Ok(())
+    }
+
+    fn size_hint<M: Serialize>(msg: &M) -> Option<usize> {
+        O::options().serialized_size(msg).ok().map(|size| size as usize)
+    }
+}
+
+impl<O: BincodeConfig> BorrowCodec for Bincode<O> {
+    fn decode_borrowed<'de, M: Deserialize<'de>>(buf: &'de [u8], limit: Option<u64>) -> std::result::Result<M, CodecError> {
+        Ok(match limit {
+            Some(limit) => O::options().with_limit(limit).deserialize(buf)?,
+            None => O::options().deserialize(buf)?,
+        })
+    }
+}
+
+/// Serializes a message by appending it to `buf`.
+///
+/// grpcio's `SerializeFn` cannot report errors, so a failure is logged and leaves `buf`
+/// untouched. Callers that need to surface the failure should [`check`] the message first.
+pub fn ser<M: Serialize, C: Codec>(msg: &M, buf: &mut Vec<u8>) {
+    let span = tracing::span!(tracing::Level::DEBUG, "serialize");
+    let _guard = span.enter();
+    let start_time = Instant::now();
+    let len = buf.len();
+    if let Some(size) = C::size_hint(msg) {
+        buf.reserve(size);
+    }
+    if let Err(e) = C::encode(msg, buf) {
+        buf.truncate(len);
+        tracing::error!("failed to serialize `{}`: {:?}", std::any::type_name::<M>(), e);
+    }
+    tracing::debug!("serialize {:?} time cost {:?} on thread {:?}", std::any::type_name_of_val(&msg), start_time.elapsed(), std::thread::current().id());
+}
+
+/// Checks that a message can be serialized.
+///
+/// Failures are returned as [`Error::Codec`] wrapping the [`CodecError`].
+pub fn check<M: Serialize, C: Codec>(msg: &M) -> Result<()> {
+    C::check(msg).map_err(|e| {
+        tracing::error!("failed to serialize `{}`: {:?}", std::any::type_name::<M>(), e);
+        Error::Codec(Box::new(e))
+    })
+}
+
+/// Serializes a request sent as raw bytes with [`raw_request`], like generated clients do with
+/// the tuple of their borrowed arguments.
+///
+/// Failures are returned as [`Error::Codec`] wrapping the [`CodecError`].
+pub fn encode<M: Serialize, C: Codec>(msg: &M) -> Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(C::size_hint(msg).unwrap_or(0));
+    C::encode(msg, &mut buf).map_err(|e| {
+        tracing::error!("failed to serialize `{}`: {:?}", std::any::type_name::<M>(), e);
+        Error::Codec(Box::new(e))
+    })?;
+    Ok(buf)
+}
+
+/// Serializes the `Ok` response of a method declared with `#[status]`, the `Err` fails the call
+/// before reaching the marshaller.
+pub fn ser_ok<T: Serialize, E, C: Codec>(msg: &std::result::Result<T, E>, buf: &mut Vec<u8>) {
+    match msg {
+        Ok(msg) => ser::<T, C>(msg, buf),
+        Err(_) => tracing::error!("`{}` errors are sent as statuses, not serialized", std::any::type_name::<E>()),
+    }
+}
+
+/// Checks that the `Ok` response of a method declared with `#[status]` can be serialized.
+pub fn check_ok<T: Serialize, E, C: Codec>(msg: &std::result::Result<T, E>) -> Result<()> {
+    match msg {
+        Ok(msg) => check::<T, C>(msg),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Deserializes the `Ok` response of a method declared with `#[status]`.
+pub fn de_ok<T: DeserializeOwned, E, C: Codec>(reader: MessageReader) -> Result<std::result::Result<T, E>> {
+    de::<T, C>(reader).map(Ok)
+}
+
+/// Deserializes a message, turning malformed payloads into an `INVALID_ARGUMENT` status
+/// instead of panicking on the completion queue thread.
+///
+/// Clients get this status as-is for malformed responses. grpcio replaces the errors of request
+/// marshallers with `INTERNAL`, so servers take raw requests, see [`raw_request`], and decode
+/// them in the handler instead.
+pub fn de<M: DeserializeOwned, C: Codec>(reader: MessageReader) -> Result<M> {
+    let len = reader.len();
+    de_read::<M, C>(reader, len)
+}
+
+/// Deserializes a message of `len` bytes read from `reader` like [`de`].
+///
+/// The message is buffered in the scratch buffer of this thread, see [`recycle`].
+pub fn de_read<M: DeserializeOwned, C: Codec>(reader: impl Read, len: usize) -> Result<M> {
+    let mut buf = scratch();
+    read_into(reader, len, &mut buf)?;
+    let result = decode::<M, C>(&buf, None).map_err(Error::RpcFailure);
+    recycle(buf);
+    result
+}
+
+/// Scratch buffers larger than this are released after use instead of being kept for the thread.
+const MAX_SCRATCH_CAPACITY: usize = 1 << 20;
+
+thread_local! {
+    static SCRATCH: Cell<Vec<u8>> = const { Cell::new(Vec::new()) };
+}
+
+/// Takes the scratch buffer of this thread, empty, or a fresh one if it is already taken.
+fn scratch() -> Vec<u8> {
+    let mut buf = SCRATCH.with(Cell::take);
+    buf.clear();
+    buf
+}
+
+/// Gives a buffer of a message back as the scratch buffer of this thread, which the next
+/// requests read by [`raw_de`] reuse instead of allocating.
+///
+/// Buffers larger than 1 MiB are released instead.
+pub fn recycle(buf: Vec<u8>) {
+    if buf.capacity() <= MAX_SCRATCH_CAPACITY {
+        SCRATCH.with(|scratch| scratch.set(buf));
+    }
+}
+
+fn read_into(mut reader: impl Read, len: usize, buf: &mut Vec<u8>) -> Result<()> {
+    buf.reserve(len);
+    reader.read_to_end(buf).map_err(|e| {
+        let details = format!("reading message from buffer failed: {}", e);
+        Error::RpcFailure(RpcStatus::new(RpcStatusCode::INVALID_ARGUMENT, Some(details)))
+    })?;
+    Ok(())
+}
+
+/// Deserializes a message from `buf`.
+///
+/// Messages larger than `limit` bytes are rejected with `RESOURCE_EXHAUSTED`, malformed
+/// messages with `INVALID_ARGUMENT`.
+pub fn decode<M: DeserializeOwned, C: Codec>(buf: &[u8], limit: Option<u64>) -> std::result::Result<M, RpcStatus> {
+    decode_with(buf, limit, C::decode)
+}
+
+/// Deserializes a message borrowing from `buf`, rejecting it like [`decode`].
+pub fn decode_borrowed<'de, M: Deserialize<'de>, C: BorrowCodec>(buf: &'de [u8], limit: Option<u64>) -> std::result::Result<M, RpcStatus> {
+    decode_with(buf, limit, C::decode_borrowed)
+}
+
+fn decode_with<'de, M>(
+    buf: &'de [u8],
+    limit: Option<u64>,
+    decode: impl FnOnce(&'de [u8], Option<u64>) -> std::result::Result<M, CodecError>,
+) -> std::result::Result<M, RpcStatus> {
+    let span = tracing::span!(tracing::Level::DEBUG, "deserialize");
+    let _guard = span.enter();
+    let start_time = Instant::now();
+    if let Some(limit) = limit {
+        if buf.len() as u64 > limit {
+            return Err(rejected::<M>(RpcStatusCode::RESOURCE_EXHAUSTED, format!("message of {} bytes exceeds the limit of {} bytes", buf.len(), limit)));
+        }
+    }
+    let result: M = decode(buf, limit).map_err(|e| match e {
+        CodecError::LimitExceeded => rejected::<M>(RpcStatusCode::RESOURCE_EXHAUSTED, format!("message exceeds the limit of {} bytes", limit.unwrap_or_default())),
+        CodecError::Invalid(e) => rejected::<M>(RpcStatusCode::INVALID_ARGUMENT, format!("{:?}", e)),
+    })?;
+    tracing::debug!("deserialize {:?} time cost {:?} on thread {:?}", std::any::type_name_of_val(&result), start_time.elapsed(), std::thread::current().id());
+    Ok(result)
+}
+
+fn rejected<M>(code: RpcStatusCode, reason: String) -> RpcStatus {
+    let details = format!("failed to deserialize `{}`: {}", std::any::type_name::<M>(), reason);
+    tracing::warn!("{}", details);
+    RpcStatus::new(code, Some(details))
+}
+
+#[allow(clippy::ptr_arg)]
+pub fn raw_ser(msg: &Vec<u8>, buf: &mut Vec<u8>) {
+    buf.extend_from_slice(msg);
+}
+
+/// Reads a raw request into the scratch buffer of this thread, handlers give it back with
+/// [`recycle`] once it is decoded.
+pub fn raw_de(reader: MessageReader) -> Result<Vec<u8>> {
+    let len = reader.len();
+    read_raw(reader, len)
+}
+
+/// Reads a raw message of `len` bytes from `reader` like [`raw_de`].
+pub fn read_raw(reader: impl Read, len: usize) -> Result<Vec<u8>> {
+    let mut buf = scratch();
+    read_into(reader, len, &mut buf)?;
+    Ok(buf)
+}
+
+/// Returns `method` with the request left as raw bytes, so that the server can decode it
+/// itself and choose the status of a rejected request: grpcio fails calls whose request
+/// marshaller returns an error with `INTERNAL`, whatever the error.
+pub fn raw_request<Req, Resp>(method: &Method<Req, Resp>) -> Method<Vec<u8>, Resp> {
+    Method {
+        ty: method.ty,
+        name: method.name,
+        req_mar: Marshaller { ser: raw_ser, de: raw_de },
+        resp_mar: Marshaller {
+            ser: method.resp_ser(),
+            de: method.resp_de(),
+        },
+    }
+}
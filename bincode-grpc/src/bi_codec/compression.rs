@@ -0,0 +1,205 @@
+use super::{Codec, CodecError};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io;
+use std::marker::PhantomData;
+
+/// Header of a frame carrying an uncompressed message.
+pub const FRAME_UNCOMPRESSED: u8 = 0xb0;
+
+/// A compression algorithm for [`Compressed`] frames.
+pub trait Compressor {
+    /// Header of a frame compressed with this algorithm.
+    const FRAME: u8;
+
+    fn compress(data: &[u8]) -> io::Result<Vec<u8>>;
+
+    /// Decompresses `data`, failing with [`CodecError::LimitExceeded`] once the output grows
+    /// beyond `limit` bytes.
+    fn decompress(data: &[u8], limit: Option<u64>) -> Result<Vec<u8>, CodecError>;
+}
+
+/// zstd at its default level, requires the `zstd` feature.
+#[cfg(feature = "zstd")]
+pub enum Zstd {}
+
+#[cfg(feature = "zstd")]
+impl Compressor for Zstd {
+    const FRAME: u8 = 0xb1;
+
+    fn compress(data: &[u8]) -> io::Result<Vec<u8>> {
+        zstd::stream::encode_all(data, 0)
+    }
+
+    fn decompress(data: &[u8], limit: Option<u64>) -> Result<Vec<u8>, CodecError> {
+        read_limited(zstd::stream::read::Decoder::new(data)?, limit)
+    }
+}
+
+/// lz4 frames, requires the `lz4` feature.
+#[cfg(feature = "lz4")]
+pub enum Lz4 {}
+
+#[cfg(feature = "lz4")]
+impl Compressor for Lz4 {
+    const FRAME: u8 = 0xb2;
+
+    fn compress(data: &[u8]) -> io::Result<Vec<u8>> {
+        use std::io::Write;
+        let mut encoder = lz4::EncoderBuilder::new().build(Vec::with_capacity(data.len()))?;
+        encoder.write_all(data)?;
+        let (compressed, result) = encoder.finish();
+        result.map(|_| compressed)
+    }
+
+    fn decompress(data: &[u8], limit: Option<u64>) -> Result<Vec<u8>, CodecError> {
+        read_limited(lz4::Decoder::new(data)?, limit)
+    }
+}
+
+#[cfg(any(feature = "zstd", feature = "lz4"))]
+fn read_limited(reader: impl io::Read, limit: Option<u64>) -> Result<Vec<u8>, CodecError> {
+    use std::io::Read;
+    let mut buf = Vec::new();
+    reader.take(limit.map_or(u64::MAX, |limit| limit + 1)).read_to_end(&mut buf)?;
+    if limit.is_some_and(|limit| buf.len() as u64 > limit) {
+        return Err(CodecError::LimitExceeded);
+    }
+    Ok(buf)
+}
+
+/// Codec `C` framed by a one-byte header, compressing messages of at least `MIN_SIZE` bytes
+/// with `A`. Used by methods marked with `#[compress(...)]`.
+///
+/// Messages are sent uncompressed when compression does not make them smaller. A frame starting
+/// with an unknown header most likely comes from a peer built without `#[compress(...)]` on the
+/// method and is rejected.
+pub struct Compressed<C, A, const MIN_SIZE: usize>(PhantomData<(C, A)>);
+
+impl<C: Codec, A: Compressor, const MIN_SIZE: usize> Codec for Compressed<C, A, MIN_SIZE> {
+    fn encode<M: Serialize>(msg: &M, buf: &mut Vec<u8>) -> Result<(), CodecError> {
+        let start = buf.len();
+        buf.push(FRAME_UNCOMPRESSED);
+        C::encode(msg, buf)?;
+        let len = buf.len() - start - 1;
+        if len >= MIN_SIZE {
+            let compressed = A::compress(&buf[start + 1..])?;
+            if compressed.len() < len {
+                buf.truncate(start);
+                buf.push(A::FRAME);
+                buf.extend_from_slice(&compressed);
+            }
+        }
+        Ok(())
+    }
+
+    fn decode<M: DeserializeOwned>(buf: &[u8], limit: Option<u64>) -> Result<M, CodecError> {
+        match buf.split_first() {
+            Some((&FRAME_UNCOMPRESSED, msg)) => C::decode(msg, limit),
+            Some((&frame, msg)) if frame == A::FRAME => C::decode(&A::decompress(msg, limit)?, limit),
+            Some((&frame, _)) => Err(CodecError::Invalid(
+                format!("unknown frame header {:#04x}, the peer may not compress this method", frame).into(),
+            )),
+            None => Err(CodecError::Invalid("missing frame header, the peer may not compress this method".into())),
+        }
+    }
+
+    fn check<M: Serialize>(msg: &M) -> Result<(), CodecError> {
+        C::check(msg)
+    }
+
+    fn size_hint<M: Serialize>(msg: &M) -> Option<usize> {
+        C::size_hint(msg).map(|size| size + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bi_codec::Bincode;
+
+    /// bytes which no algorithm makes smaller, from a xorshift generator
+    fn incompressible(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    fn encode<C: Codec>(msg: &Vec<u8>) -> Vec<u8> {
+        let mut buf = Vec::new();
+        C::encode(msg, &mut buf).unwrap();
+        buf
+    }
+
+    fn round_trip<A: Compressor>() {
+        type Framed<A> = Compressed<Bincode, A, 64>;
+        let msg = vec![7u8; 1 << 16];
+        let buf = encode::<Framed<A>>(&msg);
+        assert_eq!(buf[0], A::FRAME);
+        assert!(buf.len() < msg.len() / 10);
+        assert_eq!(Framed::<A>::decode::<Vec<u8>>(&buf, None).unwrap(), msg);
+
+        // below `MIN_SIZE`
+        let small = vec![7u8; 16];
+        let buf = encode::<Framed<A>>(&small);
+        assert_eq!(buf[0], FRAME_UNCOMPRESSED);
+        assert_eq!(&buf[1..], &bincode::serialize(&small).unwrap()[..]);
+        assert_eq!(Framed::<A>::decode::<Vec<u8>>(&buf, None).unwrap(), small);
+
+        // compression would make it larger
+        let random = incompressible(1 << 12);
+        let buf = encode::<Framed<A>>(&random);
+        assert_eq!(buf[0], FRAME_UNCOMPRESSED);
+        assert_eq!(Framed::<A>::decode::<Vec<u8>>(&buf, None).unwrap(), random);
+
+        // truncated compressed frame
+        let buf = encode::<Framed<A>>(&msg);
+        assert!(matches!(Framed::<A>::decode::<Vec<u8>>(&buf[..buf.len() / 2], None), Err(CodecError::Invalid(_))));
+
+        // a small frame decompressing beyond the decode limit
+        let limit = 1 << 10;
+        assert!(buf.len() < limit);
+        assert!(matches!(Framed::<A>::decode::<Vec<u8>>(&buf, Some(limit as u64)), Err(CodecError::LimitExceeded)));
+        assert!(matches!(A::decompress(&buf[1..], Some(limit as u64)), Err(CodecError::LimitExceeded)));
+        assert_eq!(A::decompress(&buf[1..], Some(msg.len() as u64 + 8)).unwrap(), bincode::serialize(&msg).unwrap());
+    }
+
+    fn rejects_unknown_frames<A: Compressor>() {
+        type Framed<A> = Compressed<Bincode, A, 64>;
+        let mut buf = bincode::serialize(&vec![7u8; 16]).unwrap();
+        buf.insert(0, 0xff);
+        assert!(matches!(Framed::<A>::decode::<Vec<u8>>(&buf, None), Err(CodecError::Invalid(_))));
+        assert!(matches!(Framed::<A>::decode::<Vec<u8>>(&[], None), Err(CodecError::Invalid(_))));
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd() {
+        round_trip::<Zstd>();
+        rejects_unknown_frames::<Zstd>();
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn lz4() {
+        round_trip::<Lz4>();
+        rejects_unknown_frames::<Lz4>();
+    }
+
+    #[cfg(any(feature = "zstd", feature = "lz4"))]
+    #[test]
+    fn read_limited_stops_at_the_limit() {
+        let data = vec![0u8; 1 << 20];
+        assert!(matches!(read_limited(&data[..], Some(1024)), Err(CodecError::LimitExceeded)));
+        assert_eq!(read_limited(&data[..1024], Some(1024)).unwrap().len(), 1024);
+        assert_eq!(read_limited(&data[..], None).unwrap().len(), data.len());
+        // an endless stream is cut short too
+        assert!(matches!(read_limited(std::io::repeat(0), Some(1024)), Err(CodecError::LimitExceeded)));
+    }
+}
@@ -0,0 +1,104 @@
+//! Information about the call being handled, given to methods taking a `&CallInfo` argument.
+//!
+//! grpcio only tells servers whether the deadline of a call is exceeded, see
+//! [`CallInfo::exceeded`]. Generated clients of methods taking a [`CallInfo`] also send their
+//! timeout in the [`TIMEOUT_HEADER`] header, from which servers compute an advisory
+//! [`CallInfo::deadline`] when the call is received.
+
+use crate::metadata;
+use grpcio::{CallOption, Metadata, RpcContext};
+use std::time::{Duration, Instant};
+
+/// The header carrying the timeout of a call, in milliseconds.
+///
+/// It is advisory: other clients do not send it, even with a timeout, and any client may send
+/// any value. grpc enforces the timeout of the call on its own.
+pub const TIMEOUT_HEADER: &str = "bincode-grpc-timeout";
+
+/// The metadata, peer, method and deadline of a call.
+///
+/// It is not part of the request: `&CallInfo` arguments are skipped by clients and filled in by
+/// the server.
+#[derive(Clone)]
+pub struct CallInfo {
+    method: String,
+    peer: String,
+    headers: Metadata,
+    deadline: Option<Instant>,
+    exceeded: bool,
+}
+
+impl CallInfo {
+    pub fn new(ctx: &RpcContext) -> Self {
+        let headers = ctx.request_headers().clone();
+        let deadline = metadata::find_header(&headers, TIMEOUT_HEADER)
+            .and_then(|v| std::str::from_utf8(v).ok()?.parse().ok())
+            .map(|ms| Instant::now() + Duration::from_millis(ms));
+        Self {
+            method: String::from_utf8_lossy(ctx.method()).into_owned(),
+            peer: ctx.peer(),
+            headers,
+            deadline,
+            exceeded: ctx.deadline().exceeded(),
+        }
+    }
+
+    /// The path of the method on the wire, e.g. `/helloworld.Greeter/SayHello`.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// The address of the client, e.g. `ipv4:127.0.0.1:51234`.
+    pub fn peer(&self) -> &str {
+        &self.peer
+    }
+
+    /// The headers sent by the client.
+    pub fn headers(&self) -> &Metadata {
+        &self.headers
+    }
+
+    /// The value of the first header `key`.
+    pub fn header(&self, key: &str) -> Option<&[u8]> {
+        metadata::find_header(&self.headers, key)
+    }
+
+    /// Whether the deadline of the call is exceeded: the one grpc enforces was already exceeded
+    /// when the call was received, or the advisory [`deadline`](Self::deadline) has passed.
+    pub fn exceeded(&self) -> bool {
+        self.exceeded || self.remaining() == Some(Duration::ZERO)
+    }
+
+    /// When the client gives up on the call, as sent in the advisory [`TIMEOUT_HEADER`].
+    ///
+    /// `None` for the calls of clients other than the generated ones, even when they set a
+    /// timeout, so it suits budgeting the work of a call rather than enforcing its deadline.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// The time left before the advisory deadline, zero once it is exceeded.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+}
+
+impl std::fmt::Debug for CallInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CallInfo")
+            .field("method", &self.method)
+            .field("peer", &self.peer)
+            .field("headers", &self.headers.iter().map(|(k, v)| (k, String::from_utf8_lossy(v))).collect::<Vec<_>>())
+            .field("deadline", &self.deadline)
+            .field("exceeded", &self.exceeded())
+            .finish()
+    }
+}
+
+/// Returns `opt` with its timeout, if any, added to the headers of the call.
+pub fn with_timeout(opt: CallOption) -> grpcio::Result<CallOption> {
+    match opt.get_timeout() {
+        Some(timeout) => metadata::add_header(opt, TIMEOUT_HEADER, &timeout.as_millis().to_string()),
+        None => Ok(opt),
+    }
+}
@@ -0,0 +1,76 @@
+use crate::interceptor::{self, ClientCall};
+use grpcio::{ClientUnaryReceiver, Error, RpcStatus, RpcStatusCode};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// The response of an asynchronous unary call of a generated client, e.g. `rpc_x_async`.
+///
+/// Errors raised before the call is sent, e.g. by client interceptors, are returned when the
+/// future is polled, so that the outcome of the call is a single `grpcio::Result`. Dropping the
+/// future before it completes cancels the call.
+pub struct ClientFuture<T> {
+    receiver: Option<ClientUnaryReceiver<T>>,
+    call: Option<ClientCall>,
+    error: Option<Error>,
+    map: fn(grpcio::Result<T>) -> grpcio::Result<T>,
+}
+
+impl<T> ClientFuture<T> {
+    pub fn new(receiver: ClientUnaryReceiver<T>, call: Option<ClientCall>) -> Self {
+        Self {
+            receiver: Some(receiver),
+            call,
+            error: None,
+            map: |result| result,
+        }
+    }
+
+    /// A future failing with `e` when polled.
+    pub fn failed(e: Error) -> Self {
+        Self {
+            receiver: None,
+            call: None,
+            error: Some(e),
+            map: |result| result,
+        }
+    }
+
+    /// Applies `map` to the outcome of the call.
+    pub(crate) fn map_result(mut self, map: fn(grpcio::Result<T>) -> grpcio::Result<T>) -> Self {
+        self.map = map;
+        self
+    }
+
+    /// Cancels the call, which then fails with `CANCELLED`.
+    pub fn cancel(&mut self) {
+        if let Some(receiver) = &mut self.receiver {
+            receiver.cancel();
+        }
+    }
+}
+
+impl<T> Future for ClientFuture<T> {
+    type Output = grpcio::Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let result = match &mut this.receiver {
+            Some(receiver) => futures::ready!(Pin::new(receiver).poll(cx)),
+            None => Err(this.error.take().expect("polled after completion")),
+        };
+        this.receiver = None;
+        Poll::Ready((this.map)(interceptor::finish(this.call.take(), result)))
+    }
+}
+
+impl<T> Drop for ClientFuture<T> {
+    fn drop(&mut self) {
+        if let Some(receiver) = &mut self.receiver {
+            receiver.cancel();
+            if let Some(call) = self.call.take() {
+                call.finish(&RpcStatus::new(RpcStatusCode::CANCELLED, None));
+            }
+        }
+    }
+}
@@ -0,0 +1,40 @@
+//! Glue between grpcio handlers and the generated `*_grpc` service methods.
+
+use crate::bi_codec::{self, Codec};
+use crate::sink::FailSink;
+use futures::FutureExt;
+use grpcio::{RpcContext, RpcStatus};
+use serde::de::DeserializeOwned;
+
+/// Decodes the raw request of a unary or server streaming call and passes it to `handler`.
+///
+/// A request that cannot be decoded within `limit` bytes fails the call with the status returned
+/// by [`bi_codec::decode`], e.g. `INVALID_ARGUMENT` for a malformed one, without reaching the
+/// handler. The payload is recycled once decoded, see [`bi_codec::recycle`].
+pub fn unary<Req, S, C, F>(ctx: RpcContext, payload: Vec<u8>, sink: S, limit: Option<u64>, handler: F)
+where
+    Req: DeserializeOwned,
+    S: FailSink,
+    C: Codec,
+    F: FnOnce(RpcContext, Req, S),
+{
+    let req = bi_codec::decode::<Req, C>(&payload, limit);
+    bi_codec::recycle(payload);
+    match req {
+        Ok(req) => handler(ctx, req, sink),
+        Err(status) => reject(&ctx, sink, status),
+    }
+}
+
+/// Fails a call whose request has been rejected before reaching the handler.
+///
+/// Methods with borrowed arguments decode their request in the generated handler itself, as
+/// the arguments cannot outlive it.
+pub fn reject<S: FailSink>(ctx: &RpcContext, sink: S, status: RpcStatus) {
+    let f = sink.fail(status).map(|r| {
+        if let Err(e) = r {
+            tracing::error!("failed to reply {:?}", e)
+        }
+    });
+    ctx.spawn(f)
+}
@@ -0,0 +1,291 @@
+//! Hooks run by generated services and clients around each call.
+//!
+//! Interceptors are attached with [`ServiceOptions::interceptor`](crate::ServiceOptions::interceptor)
+//! when creating a service, and with the `with_interceptor` method of generated clients. Their
+//! `before` callbacks run in the order they were added when a call starts, and their `after`
+//! callbacks run in reverse order when it ends.
+
+use crate::metadata;
+use futures::{ready, Stream};
+use grpcio::{CallOption, Metadata, RpcContext, RpcStatus, RpcStatusCode};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// A hook around the calls of a service, e.g. for auth, logging, rate limiting or metrics.
+pub trait Interceptor: Send + Sync {
+    /// Called when a call is received, before its request is decoded.
+    ///
+    /// Returning an error fails the call with that status without reaching the handler nor the
+    /// following interceptors, and no `after` callback is run for it.
+    fn before(&self, _call: &CallStart) -> Result<(), RpcStatus> {
+        Ok(())
+    }
+
+    /// Called once the call has been replied to or failed, including by a request which could
+    /// not be decoded.
+    fn after(&self, _call: &CallEnd) {}
+}
+
+/// A call as seen by [`Interceptor::before`].
+pub struct CallStart<'a> {
+    method: &'a str,
+    peer: &'a str,
+    headers: &'a Metadata,
+    request_size: Option<usize>,
+}
+
+impl CallStart<'_> {
+    /// The path of the method on the wire, e.g. `/helloworld.Greeter/SayHello`.
+    pub fn method(&self) -> &str {
+        self.method
+    }
+
+    /// The address of the client.
+    pub fn peer(&self) -> &str {
+        self.peer
+    }
+
+    /// The headers sent by the client.
+    pub fn headers(&self) -> &Metadata {
+        self.headers
+    }
+
+    /// The size in bytes of the encoded request, `None` for client streaming calls.
+    pub fn request_size(&self) -> Option<usize> {
+        self.request_size
+    }
+}
+
+/// A call as seen by [`Interceptor::after`] and [`ClientInterceptor::after`].
+pub struct CallEnd<'a> {
+    method: &'a str,
+    elapsed: Duration,
+    status: &'a RpcStatus,
+}
+
+impl CallEnd<'_> {
+    /// The path of the method on the wire, e.g. `/helloworld.Greeter/SayHello`.
+    pub fn method(&self) -> &str {
+        self.method
+    }
+
+    /// The time between the start of the call and its end, i.e. its reply on servers and the
+    /// reception of its last response on clients.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// The status the call ended with, `OK` if it succeeded.
+    pub fn status(&self) -> &RpcStatus {
+        self.status
+    }
+}
+
+/// The interceptors of a service, cheap to clone into each method handler.
+#[derive(Clone, Default)]
+pub struct Interceptors(Arc<[Arc<dyn Interceptor>]>);
+
+impl Interceptors {
+    pub(crate) fn new(interceptors: Vec<Arc<dyn Interceptor>>) -> Self {
+        Self(interceptors.into())
+    }
+
+    /// Runs the `before` callbacks for the call of `ctx`, returning the call to hand to its sink
+    /// if there are interceptors.
+    pub fn start(&self, ctx: &RpcContext, request_size: Option<usize>) -> Result<Option<InterceptedCall>, RpcStatus> {
+        if self.0.is_empty() {
+            return Ok(None);
+        }
+        let start = Instant::now();
+        let method = String::from_utf8_lossy(ctx.method()).into_owned();
+        let peer = ctx.peer();
+        let call = CallStart {
+            method: &method,
+            peer: &peer,
+            headers: ctx.request_headers(),
+            request_size,
+        };
+        for interceptor in self.0.iter() {
+            interceptor.before(&call)?;
+        }
+        Ok(Some(InterceptedCall {
+            interceptors: self.clone(),
+            method,
+            start,
+        }))
+    }
+}
+
+/// A call which passed the `before` callbacks of its interceptors, held by the sink replying
+/// to it.
+pub struct InterceptedCall {
+    interceptors: Interceptors,
+    method: String,
+    start: Instant,
+}
+
+impl InterceptedCall {
+    /// Runs the `after` callbacks once the call ended with `status`.
+    pub(crate) fn finish(self, status: &RpcStatus) {
+        let call = CallEnd {
+            method: &self.method,
+            elapsed: self.start.elapsed(),
+            status,
+        };
+        for interceptor in self.interceptors.0.iter().rev() {
+            interceptor.after(&call);
+        }
+    }
+}
+
+/// The status reported to interceptors for a call which failed in grpcio.
+pub(crate) fn error_status(e: &grpcio::Error) -> RpcStatus {
+    match e {
+        grpcio::Error::RpcFailure(status) => status.clone(),
+        e => RpcStatus::new(RpcStatusCode::UNKNOWN, Some(e.to_string())),
+    }
+}
+
+/// A hook around the calls of a generated client, e.g. to add auth tokens or trace ids to their
+/// headers or to measure their latency.
+pub trait ClientInterceptor: Send + Sync {
+    /// Called before a call of `method` is started, returning the options to call it with.
+    ///
+    /// Returning an error fails the call without sending it.
+    fn before(&self, _method: &str, opt: CallOption) -> grpcio::Result<CallOption> {
+        Ok(opt)
+    }
+
+    /// Called once the response of the call has been received, or once all of them have been
+    /// for server streaming calls.
+    fn after(&self, _call: &CallEnd) {}
+}
+
+/// The interceptors of a generated client.
+#[derive(Clone, Default)]
+pub struct ClientInterceptors(Vec<Arc<dyn ClientInterceptor>>);
+
+impl ClientInterceptors {
+    /// Adds `interceptor` after the ones already added.
+    pub fn push(&mut self, interceptor: impl ClientInterceptor + 'static) {
+        self.0.push(Arc::new(interceptor));
+    }
+
+    /// Runs the `before` callbacks for a call of `method`, returning its options and the call to
+    /// finish once it ends if there are interceptors.
+    pub fn start(&self, method: &'static str, mut opt: CallOption) -> grpcio::Result<(CallOption, Option<ClientCall>)> {
+        if self.0.is_empty() {
+            return Ok((opt, None));
+        }
+        let start = Instant::now();
+        for interceptor in self.0.iter() {
+            opt = interceptor.before(method, opt)?;
+        }
+        let call = ClientCall {
+            interceptors: self.clone(),
+            method,
+            start,
+        };
+        Ok((opt, Some(call)))
+    }
+}
+
+/// A call which passed the `before` callbacks of client interceptors.
+pub struct ClientCall {
+    interceptors: ClientInterceptors,
+    method: &'static str,
+    start: Instant,
+}
+
+impl ClientCall {
+    pub(crate) fn finish(self, status: &RpcStatus) {
+        let call = CallEnd {
+            method: self.method,
+            elapsed: self.start.elapsed(),
+            status,
+        };
+        for interceptor in self.interceptors.0.iter().rev() {
+            interceptor.after(&call);
+        }
+    }
+}
+
+/// Runs the `after` callbacks of `call` with the outcome of a blocking call.
+pub fn finish<T>(call: Option<ClientCall>, result: grpcio::Result<T>) -> grpcio::Result<T> {
+    if let Some(call) = call {
+        match &result {
+            Ok(_) => call.finish(&RpcStatus::ok()),
+            Err(e) => call.finish(&error_status(e)),
+        }
+    }
+    result
+}
+
+/// A response future or stream of a generated client, running the `after` callbacks of client
+/// interceptors once it completes.
+pub struct Intercepted<F> {
+    inner: F,
+    call: Option<ClientCall>,
+}
+
+impl<F> Intercepted<F> {
+    pub fn new(inner: F, call: Option<ClientCall>) -> Self {
+        Self { inner, call }
+    }
+
+    pub fn get_mut(&mut self) -> &mut F {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+}
+
+impl<F, T> Future for Intercepted<F>
+where
+    F: Future<Output = grpcio::Result<T>> + Unpin,
+{
+    type Output = grpcio::Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let result = ready!(Pin::new(&mut this.inner).poll(cx));
+        Poll::Ready(finish(this.call.take(), result))
+    }
+}
+
+impl<S, T> Stream for Intercepted<S>
+where
+    S: Stream<Item = grpcio::Result<T>> + Unpin,
+{
+    type Item = grpcio::Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let item = ready!(Pin::new(&mut this.inner).poll_next(cx));
+        match &item {
+            Some(Ok(_)) => {}
+            Some(Err(e)) => {
+                if let Some(call) = this.call.take() {
+                    call.finish(&error_status(e))
+                }
+            }
+            None => {
+                if let Some(call) = this.call.take() {
+                    call.finish(&RpcStatus::ok())
+                }
+            }
+        }
+        Poll::Ready(item)
+    }
+}
+
+/// Returns `opt` with the ASCII header `key` added to the headers already set on it, e.g. for
+/// [`ClientInterceptor::before`].
+pub fn with_header(opt: CallOption, key: &str, value: &str) -> grpcio::Result<CallOption> {
+    metadata::add_header(opt, key, value)
+}
@@ -0,0 +1,24 @@
+//! Helpers for the headers the generated code exchanges with its peer.
+
+use grpcio::{CallOption, Metadata, MetadataBuilder, Result};
+
+/// Returns `opt` with the ASCII header `key` added to the headers already set on it.
+pub(crate) fn add_header(opt: CallOption, key: &str, value: &str) -> Result<CallOption> {
+    let mut builder = MetadataBuilder::new();
+    if let Some(headers) = opt.get_headers() {
+        for (k, v) in headers.iter() {
+            if k.ends_with("-bin") {
+                builder.add_bytes(k, v)?;
+            } else {
+                builder.add_str(k, &String::from_utf8_lossy(v))?;
+            }
+        }
+    }
+    builder.add_str(key, value)?;
+    Ok(opt.headers(builder.build()))
+}
+
+/// The value of the first header `key` in `headers`.
+pub(crate) fn find_header<'a>(headers: &'a Metadata, key: &str) -> Option<&'a [u8]> {
+    headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(key)).map(|(_, v)| v)
+}
@@ -0,0 +1,57 @@
+use crate::interceptor::{Interceptor, Interceptors};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Runtime options of a generated service, given to `create_<service>_with_options`.
+///
+/// Values set here take precedence over the ones declared in the `#[service]` trait.
+#[derive(Clone, Default)]
+pub struct ServiceOptions {
+    decode_limit: Option<u64>,
+    method_decode_limits: HashMap<String, u64>,
+    interceptors: Vec<Arc<dyn Interceptor>>,
+}
+
+impl ServiceOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maximum size in bytes of a decoded request for all methods of the service.
+    pub fn decode_limit(mut self, limit: u64) -> Self {
+        self.decode_limit = Some(limit);
+        self
+    }
+
+    /// Maximum size in bytes of a decoded request for the method `method` (e.g. `"say_hello"`).
+    pub fn method_decode_limit(mut self, method: &str, limit: u64) -> Self {
+        self.method_decode_limits.insert(method.to_owned(), limit);
+        self
+    }
+
+    /// Decode limit of `method` set at runtime, if any.
+    pub fn get_decode_limit(&self, method: &str) -> Option<u64> {
+        self.method_decode_limits.get(method).copied().or(self.decode_limit)
+    }
+
+    /// Adds `interceptor` after the ones already added, see [`crate::interceptor`].
+    pub fn interceptor(mut self, interceptor: impl Interceptor + 'static) -> Self {
+        self.interceptors.push(Arc::new(interceptor));
+        self
+    }
+
+    /// The interceptors run around each call of the service.
+    pub fn interceptors(&self) -> Interceptors {
+        Interceptors::new(self.interceptors.clone())
+    }
+}
+
+impl std::fmt::Debug for ServiceOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ServiceOptions")
+            .field("decode_limit", &self.decode_limit)
+            .field("method_decode_limits", &self.method_decode_limits)
+            .field("interceptors", &self.interceptors.len())
+            .finish()
+    }
+}
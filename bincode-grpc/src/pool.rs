@@ -0,0 +1,103 @@
+use grpcio::{RpcStatus, RpcStatusCode};
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Thread pool running the handlers of `#[blocking]` methods and of `#[server(blocking_pool = N)]`
+/// impls, so that they don't hold the completion queue threads of grpcio. The handlers of client
+/// streaming methods run on a pool of their own, see `#[server(streaming_pool = N)]`.
+///
+/// The threads are plain threads outside of any executor, so handlers may block, e.g. on
+/// synchronous client calls or on the items of a `ClientStream`.
+///
+/// Threads are started by [`start`](Self::start), which handlers call before queuing their job so
+/// that a pool unable to start fails the call instead of panicking on the completion queue.
+pub struct BlockingPool {
+    size: Option<usize>,
+    threads: Mutex<usize>,
+    jobs: Mutex<VecDeque<Job>>,
+    queued: Condvar,
+}
+
+impl BlockingPool {
+    /// A pool with one thread per CPU.
+    pub const fn new() -> Self {
+        Self::with_threads(None)
+    }
+
+    /// A pool with `size` threads.
+    pub const fn with_size(size: usize) -> Self {
+        Self::with_threads(Some(size))
+    }
+
+    const fn with_threads(size: Option<usize>) -> Self {
+        Self {
+            size,
+            threads: Mutex::new(0),
+            jobs: Mutex::new(VecDeque::new()),
+            queued: Condvar::new(),
+        }
+    }
+
+    /// Starts the threads of the pool which are not running yet.
+    ///
+    /// Fails with `RESOURCE_EXHAUSTED` if the pool has no thread and none can be started, a pool
+    /// with some of its threads runs with them and tries to start the others on the next call.
+    pub fn start(&'static self) -> Result<&'static Self, RpcStatus> {
+        let size = self.size.unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()));
+        let mut threads = lock(&self.threads);
+        while *threads < size {
+            let name = format!("bincode-grpc-blocking-{}", *threads);
+            match thread::Builder::new().name(name).spawn(move || self.work()) {
+                Ok(_) => *threads += 1,
+                Err(e) => {
+                    tracing::error!("cannot start a thread of the blocking pool: {}", e);
+                    break;
+                }
+            }
+        }
+        if *threads == 0 {
+            return Err(RpcStatus::new(RpcStatusCode::RESOURCE_EXHAUSTED, Some("cannot start the blocking pool".to_owned())));
+        }
+        Ok(self)
+    }
+
+    /// Queues `f`, which runs once a thread of the pool is free. Jobs only run once the pool is
+    /// started.
+    pub fn spawn<F: FnOnce() + Send + 'static>(&self, f: F) {
+        lock(&self.jobs).push_back(Box::new(f));
+        self.queued.notify_one();
+    }
+
+    /// Runs the queued jobs, a panicking handler only fails its own call.
+    fn work(&self) {
+        loop {
+            let job = {
+                let mut jobs = lock(&self.jobs);
+                loop {
+                    match jobs.pop_front() {
+                        Some(job) => break job,
+                        None => jobs = self.queued.wait(jobs).unwrap_or_else(PoisonError::into_inner),
+                    }
+                }
+            };
+            if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+                tracing::error!("a blocking handler panicked");
+            }
+        }
+    }
+}
+
+impl Default for BlockingPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// the jobs and threads stay consistent across a panic, which only happens in the handlers
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
@@ -0,0 +1,154 @@
+//! Retries of the blocking unary calls of generated clients.
+//!
+//! Only methods declared with `#[idempotent]` or `#[retry(...)]` in the `#[service]` trait are
+//! retried, with the policy set on the client by `with_retry_policy` and the overrides given to
+//! `#[retry(...)]`:
+//!
+//! ```ignore
+//! #[retry(max_attempts = 5, initial_backoff_ms = 100, codes(UNAVAILABLE, ABORTED))]
+//! fn get_item(&mut self, id: u64) -> Item;
+//! ```
+//!
+//! The timeout of the call bounds all attempts and backoffs together. The asynchronous
+//! `*_async` client methods make a single attempt, retry them by calling them again.
+
+use grpcio::{CallOption, Error, RpcStatusCode};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, Instant};
+
+/// How failed calls are retried: up to `max_attempts` calls in total, waiting an exponentially
+/// growing backoff with random jitter between them, for calls failing with a retryable status.
+///
+/// The default policy makes 3 attempts for `UNAVAILABLE` calls, backing off from 50ms to 1s.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    jitter: f64,
+    /// bit `n` is set for the status code `n`
+    codes: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_secs(1),
+            multiplier: 2.0,
+            jitter: 0.2,
+            codes: 0,
+        }
+        .retry_on(&[RpcStatusCode::UNAVAILABLE])
+    }
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A policy making a single attempt.
+    pub fn never() -> Self {
+        Self::default().max_attempts(1)
+    }
+
+    /// The number of calls made in total, including the first one.
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// The backoff before the first retry.
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// The cap on the backoff between two attempts.
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// The factor applied to the backoff after each retry.
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// The fraction of the backoff randomly added or removed, between 0 and 1.
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Retries calls failing with one of `codes`, instead of the codes set before.
+    pub fn retry_on(mut self, codes: &[RpcStatusCode]) -> Self {
+        self.codes = codes.iter().fold(0, |codes, &code| codes | code_bit(code));
+        self
+    }
+
+    /// Whether the call failing with `e` after `attempt` attempts should be retried.
+    pub fn should_retry(&self, e: &Error, attempt: u32) -> bool {
+        match e {
+            Error::RpcFailure(status) => attempt < self.max_attempts && self.codes & code_bit(status.status) != 0,
+            _ => false,
+        }
+    }
+
+    /// The backoff after the `attempt`-th failed attempt, starting from 1.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let backoff = (self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent)).min(self.max_backoff.as_secs_f64());
+        let jitter = backoff * self.jitter * (random_unit() * 2.0 - 1.0);
+        Duration::from_secs_f64((backoff + jitter).max(0.0))
+    }
+}
+
+fn code_bit(code: RpcStatusCode) -> u32 {
+    let code: i32 = code.into();
+    1u32.checked_shl(code as u32).unwrap_or(0)
+}
+
+/// A random number in `[0, 1)`, good enough to spread retries.
+fn random_unit() -> f64 {
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Calls `call` with `opt` until it succeeds or `policy` gives up, sleeping between attempts.
+///
+/// The timeout of `opt` is the deadline of the whole call: each attempt is given the time left
+/// before it, and the call fails with the last error once the next backoff would pass it.
+pub fn retry<T>(policy: &RetryPolicy, mut opt: CallOption, mut call: impl FnMut(CallOption) -> grpcio::Result<T>) -> grpcio::Result<T> {
+    let deadline = opt.get_timeout().map(|timeout| Instant::now() + timeout);
+    let mut attempt = 1;
+    loop {
+        match call(opt.clone()) {
+            Err(e) if policy.should_retry(&e, attempt) => {
+                let backoff = policy.backoff(attempt);
+                if let Some(deadline) = deadline {
+                    if Instant::now() + backoff >= deadline {
+                        tracing::debug!("not retrying after attempt {} failed, the deadline is exhausted: {:?}", attempt, e);
+                        return Err(e);
+                    }
+                }
+                tracing::debug!("retrying in {:?} after attempt {} failed: {:?}", backoff, attempt, e);
+                std::thread::sleep(backoff);
+                if let Some(deadline) = deadline {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        return Err(e);
+                    }
+                    opt = opt.timeout(remaining);
+                }
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
@@ -0,0 +1,299 @@
+//! Fingerprints of the request and response types of a method.
+//!
+//! bincode is not self-describing, so a client and a server built against different versions
+//! of a message decode each other's bytes into garbage. Services declared with
+//! `#[service(fingerprint)]` send a hash of the [`Schema`] of both types in a header of each
+//! call, and the server rejects calls whose fingerprint does not match its own with
+//! `FAILED_PRECONDITION`.
+
+use crate::metadata;
+use grpcio::{CallOption, RpcContext, RpcStatus, RpcStatusCode};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::sync::{Arc, OnceLock};
+
+/// The header carrying the fingerprint of a call, as 16 hex digits.
+pub const FINGERPRINT_HEADER: &str = "bincode-grpc-fingerprint";
+
+/// The shape of a type as seen by its serialized form.
+///
+/// `#[derive(Schema)]` describes a struct or enum by its name, its fields and their types, so
+/// renaming any of them changes the fingerprint even if the encoding stays the same. Fields
+/// marked `#[serde(skip)]` are left out. A type appearing within its own description, e.g.
+/// `struct Node { children: Vec<Node> }`, is described by a back-reference to its name there.
+pub trait Schema {
+    /// Feeds the description of `Self` to `f`.
+    fn describe(f: &mut Fingerprint);
+}
+
+/// A 64-bit FNV-1a hash of schema descriptions.
+pub struct Fingerprint {
+    hash: u64,
+    /// the named types being described
+    visiting: Vec<&'static str>,
+}
+
+impl Fingerprint {
+    pub fn new() -> Self {
+        Fingerprint { hash: 0xcbf2_9ce4_8422_2325, visiting: vec![] }
+    }
+
+    pub fn write(&mut self, s: &str) {
+        for b in s.bytes() {
+            self.hash ^= u64::from(b);
+            self.hash = self.hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    /// Writes `name` followed by the description of the type `key`, or a back-reference to
+    /// `name` if `key` is already being described, so that recursive types end.
+    ///
+    /// `key` identifies the type including its generic arguments, e.g. its `type_name`.
+    pub fn named(&mut self, key: &'static str, name: &str, describe: impl FnOnce(&mut Self)) {
+        if self.visiting.contains(&key) {
+            self.write("^");
+            self.write(name);
+            return;
+        }
+        self.write(name);
+        self.visiting.push(key);
+        describe(self);
+        self.visiting.pop();
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+impl Default for Fingerprint {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The fingerprint of a method taking `Req` and returning `Resp`.
+pub fn fingerprint<Req: Schema, Resp: Schema>() -> u64 {
+    let mut f = Fingerprint::new();
+    Req::describe(&mut f);
+    f.write("->");
+    Resp::describe(&mut f);
+    f.finish()
+}
+
+/// Returns `opt` with the fingerprint header of a call added.
+pub fn with_fingerprint(opt: CallOption, fingerprint: u64) -> grpcio::Result<CallOption> {
+    metadata::add_header(opt, FINGERPRINT_HEADER, &format!("{:016x}", fingerprint))
+}
+
+/// The fingerprint header of a method, computed on its first call and reused by the following
+/// ones. Generated clients keep one in a static per method.
+pub struct CachedFingerprint(OnceLock<String>);
+
+impl CachedFingerprint {
+    pub const fn new() -> Self {
+        CachedFingerprint(OnceLock::new())
+    }
+
+    /// Returns `opt` with the header of the `fingerprint` of the method added.
+    pub fn with_header(&self, opt: CallOption, fingerprint: impl FnOnce() -> u64) -> grpcio::Result<CallOption> {
+        let value = self.0.get_or_init(|| format!("{:016x}", fingerprint()));
+        metadata::add_header(opt, FINGERPRINT_HEADER, value)
+    }
+}
+
+impl Default for CachedFingerprint {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Checks the fingerprint sent by the client of `method` against the `expected` one of the server.
+///
+/// Calls without a fingerprint, made by clients of services declared without the option, are let
+/// through.
+pub fn check_fingerprint(ctx: &RpcContext, method: &str, expected: u64) -> Result<(), RpcStatus> {
+    let value = match metadata::find_header(ctx.request_headers(), FINGERPRINT_HEADER) {
+        Some(value) => value,
+        None => return Ok(()),
+    };
+    let actual = std::str::from_utf8(value).ok().and_then(|v| u64::from_str_radix(v, 16).ok()).ok_or_else(|| {
+        let details = format!("`{}` was called with a malformed fingerprint {:?}", method, String::from_utf8_lossy(value));
+        RpcStatus::new(RpcStatusCode::INVALID_ARGUMENT, Some(details))
+    })?;
+    if actual == expected {
+        Ok(())
+    } else {
+        let details = format!(
+            "`{}` was called with fingerprint {:016x} but the server expects {:016x}, the client and the server disagree on the request or response types",
+            method, actual, expected
+        );
+        tracing::warn!("{}", details);
+        Err(RpcStatus::new(RpcStatusCode::FAILED_PRECONDITION, Some(details)))
+    }
+}
+
+macro_rules! named_schema {
+    ($($ty:ty => $name:expr),* $(,)?) => {
+        $(
+            impl Schema for $ty {
+                fn describe(f: &mut Fingerprint) {
+                    f.write($name);
+                }
+            }
+        )*
+    };
+}
+
+named_schema! {
+    () => "()",
+    bool => "bool",
+    char => "char",
+    i8 => "i8",
+    i16 => "i16",
+    i32 => "i32",
+    i64 => "i64",
+    i128 => "i128",
+    isize => "i64",
+    u8 => "u8",
+    u16 => "u16",
+    u32 => "u32",
+    u64 => "u64",
+    u128 => "u128",
+    usize => "u64",
+    f32 => "f32",
+    f64 => "f64",
+    str => "string",
+    String => "string",
+    bytes::Bytes => "[u8]",
+}
+
+macro_rules! transparent_schema {
+    ($($ty:ident),*) => {
+        $(
+            impl<T: Schema + ?Sized> Schema for $ty<T> {
+                fn describe(f: &mut Fingerprint) {
+                    T::describe(f)
+                }
+            }
+        )*
+    };
+}
+
+transparent_schema!(Box, Rc, Arc);
+
+impl<T: Schema + ?Sized> Schema for &T {
+    fn describe(f: &mut Fingerprint) {
+        T::describe(f)
+    }
+}
+
+impl<T: Schema + ToOwned + ?Sized> Schema for Cow<'_, T> {
+    fn describe(f: &mut Fingerprint) {
+        T::describe(f)
+    }
+}
+
+impl<T: ?Sized> Schema for PhantomData<T> {
+    fn describe(f: &mut Fingerprint) {
+        f.write("()")
+    }
+}
+
+macro_rules! seq_schema {
+    ($($ty:ident),*) => {
+        $(
+            impl<T: Schema> Schema for $ty<T> {
+                fn describe(f: &mut Fingerprint) {
+                    f.write("[");
+                    T::describe(f);
+                    f.write("]");
+                }
+            }
+        )*
+    };
+}
+
+seq_schema!(Vec, VecDeque, HashSet, BTreeSet);
+
+impl<T: Schema> Schema for [T] {
+    fn describe(f: &mut Fingerprint) {
+        f.write("[");
+        T::describe(f);
+        f.write("]");
+    }
+}
+
+impl<T: Schema, const N: usize> Schema for [T; N] {
+    fn describe(f: &mut Fingerprint) {
+        f.write("[");
+        T::describe(f);
+        f.write(&format!(";{}]", N));
+    }
+}
+
+macro_rules! map_schema {
+    ($($ty:ident),*) => {
+        $(
+            impl<K: Schema, V: Schema> Schema for $ty<K, V> {
+                fn describe(f: &mut Fingerprint) {
+                    f.write("{");
+                    K::describe(f);
+                    f.write(":");
+                    V::describe(f);
+                    f.write("}");
+                }
+            }
+        )*
+    };
+}
+
+map_schema!(HashMap, BTreeMap);
+
+impl<T: Schema> Schema for Option<T> {
+    fn describe(f: &mut Fingerprint) {
+        f.write("option<");
+        T::describe(f);
+        f.write(">");
+    }
+}
+
+impl<T: Schema, E: Schema> Schema for Result<T, E> {
+    fn describe(f: &mut Fingerprint) {
+        f.write("result<");
+        T::describe(f);
+        f.write(",");
+        E::describe(f);
+        f.write(">");
+    }
+}
+
+macro_rules! tuple_schema {
+    ($($name:ident)+) => {
+        impl<$($name: Schema),+> Schema for ($($name,)+) {
+            fn describe(f: &mut Fingerprint) {
+                f.write("(");
+                $(
+                    $name::describe(f);
+                    f.write(",");
+                )+
+                f.write(")");
+            }
+        }
+    };
+}
+
+tuple_schema!(A);
+tuple_schema!(A B);
+tuple_schema!(A B C);
+tuple_schema!(A B C D);
+tuple_schema!(A B C D E);
+tuple_schema!(A B C D E F);
+tuple_schema!(A B C D E F G);
+tuple_schema!(A B C D E F G H);
+tuple_schema!(A B C D E F G H I);
+tuple_schema!(A B C D E F G H I J);
+tuple_schema!(A B C D E F G H I J K);
+tuple_schema!(A B C D E F G H I J K L);
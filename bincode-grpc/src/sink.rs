@@ -0,0 +1,233 @@
+use crate::interceptor::{self, InterceptedCall};
+use crate::stream::ServerStream;
+use futures::future::Either;
+use futures::{FutureExt, SinkExt, StreamExt};
+use grpcio::{ClientStreamingSinkResult, RpcContext, RpcStatus, RpcStatusCode, UnarySinkResult, WriteFlags};
+use std::future::Future;
+
+/// A sink of the generated handlers, which can fail calls rejected before reaching the service.
+pub trait FailSink {
+    type Failure: Future<Output = grpcio::Result<()>> + Send + 'static;
+
+    fn fail(self, status: RpcStatus) -> Self::Failure;
+}
+
+/// The reply handle given to the generated `*_grpc` service methods.
+///
+/// It wraps [`grpcio::UnarySink`] and checks that the response can be serialized before handing
+/// it to grpcio, so a failing `Serialize` impl fails the call with `INTERNAL` instead of tearing
+/// down the completion queue thread inside the marshaller.
+///
+/// Client streaming methods, which also reply with a single response, use it as well.
+pub struct UnarySink<T> {
+    sink: RawSink<T>,
+    check: fn(&T) -> grpcio::Result<()>,
+    reply: fn(T) -> Result<T, RpcStatus>,
+    call: Option<InterceptedCall>,
+}
+
+/// The grpcio sinks wrapped by a [`UnarySink`].
+pub enum RawSink<T> {
+    Unary(grpcio::UnarySink<T>),
+    ClientStreaming(grpcio::ClientStreamingSink<T>),
+}
+
+impl<T> From<grpcio::UnarySink<T>> for RawSink<T> {
+    fn from(sink: grpcio::UnarySink<T>) -> Self {
+        RawSink::Unary(sink)
+    }
+}
+
+impl<T> From<grpcio::ClientStreamingSink<T>> for RawSink<T> {
+    fn from(sink: grpcio::ClientStreamingSink<T>) -> Self {
+        RawSink::ClientStreaming(sink)
+    }
+}
+
+/// The future returned by [`UnarySink::success`] and [`UnarySink::fail`].
+pub type SinkResult = Either<UnarySinkResult, ClientStreamingSinkResult>;
+
+impl<T> RawSink<T> {
+    fn success(self, t: T) -> SinkResult {
+        match self {
+            RawSink::Unary(sink) => Either::Left(sink.success(t)),
+            RawSink::ClientStreaming(sink) => Either::Right(sink.success(t)),
+        }
+    }
+
+    fn fail(self, status: RpcStatus) -> SinkResult {
+        match self {
+            RawSink::Unary(sink) => Either::Left(sink.fail(status)),
+            RawSink::ClientStreaming(sink) => Either::Right(sink.fail(status)),
+        }
+    }
+}
+
+impl<T> UnarySink<T> {
+    pub fn new(sink: impl Into<RawSink<T>>, check: fn(&T) -> grpcio::Result<()>) -> Self {
+        Self::with_reply(sink, check, Ok)
+    }
+
+    /// A sink failing the call with the status returned by `reply` instead of sending the
+    /// response, used by methods declared with `#[status]`.
+    pub fn with_reply(sink: impl Into<RawSink<T>>, check: fn(&T) -> grpcio::Result<()>, reply: fn(T) -> Result<T, RpcStatus>) -> Self {
+        Self { sink: sink.into(), check, reply, call: None }
+    }
+
+    /// Reports the end of the call to the interceptors of `call`.
+    pub fn intercepted(self, call: Option<InterceptedCall>) -> Self {
+        Self { call, ..self }
+    }
+
+    pub fn success(self, t: T) -> SinkResult {
+        let t = match (self.reply)(t) {
+            Ok(t) => t,
+            Err(status) => return self.fail(status),
+        };
+        match (self.check)(&t) {
+            Ok(()) => {
+                if let Some(call) = self.call {
+                    call.finish(&RpcStatus::ok());
+                }
+                self.sink.success(t)
+            }
+            Err(e) => self.fail(serialize_failed::<T>(e)),
+        }
+    }
+
+    pub fn fail(self, status: RpcStatus) -> SinkResult {
+        if let Some(call) = self.call {
+            call.finish(&status);
+        }
+        self.sink.fail(status)
+    }
+}
+
+impl<T> FailSink for UnarySink<T> {
+    type Failure = SinkResult;
+
+    fn fail(self, status: RpcStatus) -> SinkResult {
+        UnarySink::fail(self, status)
+    }
+}
+
+/// The response handle given to the generated `*_grpc` methods of server and bidirectional
+/// streaming methods.
+///
+/// Like [`UnarySink`], each response is checked before being handed to grpcio and a response
+/// which cannot be serialized fails the call with `INTERNAL`.
+pub struct ServerStreamingSink<T> {
+    sink: RawStreamingSink<T>,
+    check: fn(&T) -> grpcio::Result<()>,
+    call: Option<InterceptedCall>,
+}
+
+/// The grpcio sinks wrapped by a [`ServerStreamingSink`].
+pub enum RawStreamingSink<T> {
+    ServerStreaming(grpcio::ServerStreamingSink<T>),
+    Duplex(grpcio::DuplexSink<T>),
+}
+
+impl<T> From<grpcio::ServerStreamingSink<T>> for RawStreamingSink<T> {
+    fn from(sink: grpcio::ServerStreamingSink<T>) -> Self {
+        RawStreamingSink::ServerStreaming(sink)
+    }
+}
+
+impl<T> From<grpcio::DuplexSink<T>> for RawStreamingSink<T> {
+    fn from(sink: grpcio::DuplexSink<T>) -> Self {
+        RawStreamingSink::Duplex(sink)
+    }
+}
+
+impl<T: Send + 'static> ServerStreamingSink<T> {
+    pub fn new(sink: impl Into<RawStreamingSink<T>>, check: fn(&T) -> grpcio::Result<()>) -> Self {
+        Self { sink: sink.into(), check, call: None }
+    }
+
+    /// Reports the end of the call to the interceptors of `call`.
+    pub fn intercepted(self, call: Option<InterceptedCall>) -> Self {
+        Self { call, ..self }
+    }
+
+    /// Sends the responses of `stream` on the poll thread of `ctx`, waiting for the client to
+    /// receive them as the stream goes.
+    pub fn send_stream(self, ctx: &RpcContext, stream: ServerStream<T>) {
+        ctx.spawn(self.send_all(stream).map(|r| {
+            if let Err(e) = r {
+                tracing::error!("failed to stream responses {:?}", e)
+            }
+        }))
+    }
+
+    /// Sends the responses of `stream` and closes the call, the future resolves once they have
+    /// all been received by the client.
+    pub async fn send_all(self, stream: ServerStream<T>) -> grpcio::Result<()> {
+        let result = match self.sink {
+            RawStreamingSink::ServerStreaming(sink) => send_all(sink, self.check, stream).await,
+            RawStreamingSink::Duplex(sink) => send_all(sink, self.check, stream).await,
+        };
+        if let Some(call) = self.call {
+            match &result {
+                Ok(status) => call.finish(status),
+                Err(e) => call.finish(&interceptor::error_status(e)),
+            }
+        }
+        result.map(|_| ())
+    }
+}
+
+/// Sends the responses of `stream` to `sink`, returning the status the call ended with.
+async fn send_all<S, T>(mut sink: S, check: fn(&T) -> grpcio::Result<()>, stream: ServerStream<T>) -> grpcio::Result<RpcStatus>
+where
+    S: futures::Sink<(T, WriteFlags), Error = grpcio::Error> + FailSink + Unpin,
+{
+    let mut stream = stream.into_inner();
+    while let Some(item) = stream.next().await {
+        let item = item.and_then(|t| check(&t).map(|()| t).map_err(serialize_failed::<T>));
+        match item {
+            Ok(t) => sink.send((t, WriteFlags::default())).await?,
+            Err(status) => {
+                sink.fail(status.clone()).await?;
+                return Ok(status);
+            }
+        }
+    }
+    sink.close().await?;
+    Ok(RpcStatus::ok())
+}
+
+impl<T> FailSink for ServerStreamingSink<T> {
+    type Failure = Either<grpcio::ServerStreamingSinkFailure, grpcio::DuplexSinkFailure>;
+
+    fn fail(self, status: RpcStatus) -> Self::Failure {
+        if let Some(call) = self.call {
+            call.finish(&status);
+        }
+        match self.sink {
+            RawStreamingSink::ServerStreaming(sink) => Either::Left(sink.fail(status)),
+            RawStreamingSink::Duplex(sink) => Either::Right(sink.fail(status)),
+        }
+    }
+}
+
+impl<T> FailSink for grpcio::ServerStreamingSink<T> {
+    type Failure = grpcio::ServerStreamingSinkFailure;
+
+    fn fail(self, status: RpcStatus) -> Self::Failure {
+        grpcio::ServerStreamingSink::fail(self, status)
+    }
+}
+
+impl<T> FailSink for grpcio::DuplexSink<T> {
+    type Failure = grpcio::DuplexSinkFailure;
+
+    fn fail(self, status: RpcStatus) -> Self::Failure {
+        grpcio::DuplexSink::fail(self, status)
+    }
+}
+
+fn serialize_failed<T>(e: grpcio::Error) -> RpcStatus {
+    let details = format!("failed to serialize `{}`: {}", std::any::type_name::<T>(), e);
+    RpcStatus::new(RpcStatusCode::INTERNAL, Some(details))
+}
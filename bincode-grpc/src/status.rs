@@ -0,0 +1,67 @@
+//! Errors of methods declared with `#[status]`, returned as gRPC statuses.
+//!
+//! By default a method returning `Result<T, E>` serializes the whole `Result`, so failed calls
+//! still succeed at the gRPC level. With `#[status]` the `Err` fails the call with the status
+//! given by [`RpcError::into_status`], and the client turns it back into `Err` with
+//! [`RpcError::from_status`]. Statuses which are not errors of the method stay an `Err` of the
+//! call, which `#[idempotent]` and `#[retry(...)]` methods retry.
+
+use crate::ClientFuture;
+use grpcio::{Error, RpcStatus, RpcStatusCode};
+
+/// The error type of a method declared with `#[status]`.
+pub trait RpcError: Sized {
+    /// The status the call fails with.
+    fn into_status(self) -> RpcStatus;
+
+    /// The error represented by a status received by the client, `None` for statuses which are
+    /// not errors of the method, e.g. `UNAVAILABLE` raised by the transport.
+    fn from_status(status: &RpcStatus) -> Option<Self>;
+}
+
+/// Statuses are errors of the method unless their code is one raised by grpcio or the
+/// transport, which stay `Err` at the gRPC level so that they can be retried: `CANCELLED`,
+/// `UNKNOWN`, `DEADLINE_EXCEEDED`, `RESOURCE_EXHAUSTED`, `UNIMPLEMENTED`, `INTERNAL` and
+/// `UNAVAILABLE`. Methods should not return those codes as their errors.
+impl RpcError for RpcStatus {
+    fn into_status(self) -> RpcStatus {
+        self
+    }
+
+    fn from_status(status: &RpcStatus) -> Option<Self> {
+        match status.status {
+            RpcStatusCode::CANCELLED
+            | RpcStatusCode::UNKNOWN
+            | RpcStatusCode::DEADLINE_EXCEEDED
+            | RpcStatusCode::RESOURCE_EXHAUSTED
+            | RpcStatusCode::UNIMPLEMENTED
+            | RpcStatusCode::INTERNAL
+            | RpcStatusCode::UNAVAILABLE => None,
+            _ => Some(status.clone()),
+        }
+    }
+}
+
+/// Turns the `Err` returned by a method into the status failing the call.
+pub fn into_reply<T, E: RpcError>(resp: Result<T, E>) -> Result<Result<T, E>, RpcStatus> {
+    match resp {
+        Ok(t) => Ok(Ok(t)),
+        Err(e) => Err(e.into_status()),
+    }
+}
+
+/// Turns the status of a failed call back into the `Err` of the method.
+pub fn from_reply<T, E: RpcError>(resp: grpcio::Result<Result<T, E>>) -> grpcio::Result<Result<T, E>> {
+    match resp {
+        Err(Error::RpcFailure(status)) => match E::from_status(&status) {
+            Some(e) => Ok(Err(e)),
+            None => Err(Error::RpcFailure(status)),
+        },
+        resp => resp,
+    }
+}
+
+/// Maps the outcome of the asynchronous call `future` with [`from_reply`].
+pub fn future<T, E: RpcError>(future: ClientFuture<Result<T, E>>) -> ClientFuture<Result<T, E>> {
+    future.map_result(from_reply)
+}
@@ -0,0 +1,142 @@
+//! Message streams of streaming methods.
+
+use crate::bi_codec::{self, Codec};
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use futures::{ready, Sink};
+use grpcio::{ClientCStreamSender, Error, RequestStream, RpcStatus, WriteFlags};
+use serde::de::DeserializeOwned;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// The responses of a server streaming method, returned by the method instead of `T`.
+///
+/// ```ignore
+/// fn watch(&mut self, from: u64) -> ServerStream<Event>;
+/// ```
+///
+/// The call ends successfully with the stream, or with the status of the first `Err` of a
+/// stream built by [`ServerStream::try_new`].
+pub struct ServerStream<T> {
+    inner: BoxStream<'static, Result<T, RpcStatus>>,
+}
+
+impl<T: Send + 'static> ServerStream<T> {
+    pub fn new(stream: impl Stream<Item = T> + Send + 'static) -> Self {
+        Self::try_new(stream.map(Ok))
+    }
+
+    pub fn try_new(stream: impl Stream<Item = Result<T, RpcStatus>> + Send + 'static) -> Self {
+        Self { inner: stream.boxed() }
+    }
+
+    /// Streams the items of `iter`.
+    pub fn iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: Send + 'static,
+    {
+        Self::new(stream::iter(iter))
+    }
+
+    /// Fails the call with `status` without sending any response.
+    pub fn fail(status: RpcStatus) -> Self {
+        Self::try_new(stream::once(async { Err(status) }))
+    }
+}
+
+impl<T> ServerStream<T> {
+    pub(crate) fn into_inner(self) -> BoxStream<'static, Result<T, RpcStatus>> {
+        self.inner
+    }
+}
+
+/// The requests of a client streaming method, taken by the method as its only argument.
+///
+/// ```ignore
+/// fn upload(&self, chunks: ClientStream<Chunk>) -> Summary;
+/// ```
+///
+/// It is both a `Stream` and a blocking `Iterator` of the requests. A request that cannot be
+/// decoded within the decode limit of the method is yielded as the `Err` status it would have
+/// failed a unary call with. Synchronous methods iterating the requests run on the streaming
+/// `BlockingPool` of the server, holding one of its threads until the client closes the stream,
+/// `async` ones await them on the completion queue instead.
+pub struct ClientStream<T> {
+    inner: RequestStream<Vec<u8>>,
+    limit: Option<u64>,
+    decode: fn(&[u8], Option<u64>) -> Result<T, RpcStatus>,
+}
+
+impl<T: DeserializeOwned> ClientStream<T> {
+    pub fn new<C: Codec>(inner: RequestStream<Vec<u8>>, limit: Option<u64>) -> Self {
+        Self { inner, limit, decode: bi_codec::decode::<T, C> }
+    }
+}
+
+impl<T> Stream for ClientStream<T> {
+    type Item = grpcio::Result<T>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        Poll::Ready(match ready!(Pin::new(&mut this.inner).poll_next(cx)) {
+            Some(Ok(buf)) => {
+                let item = (this.decode)(&buf, this.limit).map_err(Error::RpcFailure);
+                bi_codec::recycle(buf);
+                Some(item)
+            }
+            Some(Err(e)) => Some(Err(e)),
+            None => None,
+        })
+    }
+}
+
+/// Blocks until the next request is received, which must not happen on a grpcio poll thread.
+impl<T> Iterator for ClientStream<T> {
+    type Item = grpcio::Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        futures::executor::block_on(StreamExt::next(self))
+    }
+}
+
+/// The request sender of a client streaming call.
+///
+/// Like the unary client methods, each request is checked before being handed to grpcio and a
+/// request which cannot be serialized is refused with [`Error::Codec`].
+pub struct StreamSender<T> {
+    sink: ClientCStreamSender<T>,
+    check: fn(&T) -> grpcio::Result<()>,
+}
+
+impl<T> StreamSender<T> {
+    pub fn new(sink: ClientCStreamSender<T>, check: fn(&T) -> grpcio::Result<()>) -> Self {
+        Self { sink, check }
+    }
+
+    /// Cancels the call.
+    pub fn cancel(&mut self) {
+        self.sink.cancel()
+    }
+}
+
+impl<T> Sink<T> for StreamSender<T> {
+    type Error = Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<grpcio::Result<()>> {
+        Pin::new(&mut self.sink).poll_ready(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: T) -> grpcio::Result<()> {
+        (self.check)(&item)?;
+        Pin::new(&mut self.sink).start_send((item, WriteFlags::default()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<grpcio::Result<()>> {
+        Pin::new(&mut self.sink).poll_flush(cx)
+    }
+
+    /// Ends the requests, after which the server replies.
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<grpcio::Result<()>> {
+        Pin::new(&mut self.sink).poll_close(cx)
+    }
+}
@@ -0,0 +1,291 @@
+use futures::{FutureExt, TryFutureExt};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, bincode_grpc::Schema)]
+pub struct Input {}
+
+#[derive(Serialize, Deserialize, Debug, bincode_grpc::Schema)]
+pub struct Output {}
+
+/// A request decoded by borrowing its fields from the received message.
+#[derive(Serialize, Deserialize, Debug, bincode_grpc::Schema)]
+pub struct Chunk<'a> {
+    pub name: &'a str,
+    #[serde(borrow)]
+    pub data: &'a [u8],
+}
+
+/// A message which cannot be serialized when `fail` is set, failing the call instead of the peer.
+#[derive(Serialize, Deserialize, Debug, bincode_grpc::Schema)]
+pub struct Fallible {
+    #[serde(serialize_with = "serialize_fallible")]
+    pub fail: bool,
+}
+
+fn serialize_fallible<S: serde::Serializer>(fail: &bool, serializer: S) -> Result<S::Ok, S::Error> {
+    if *fail {
+        Err(serde::ser::Error::custom("refusing to serialize"))
+    } else {
+        serializer.serialize_bool(false)
+    }
+}
+
+#[bincode_grpc::service(fingerprint, package = "example", legacy_names)]
+pub trait TestService {
+    #[idempotent]
+    fn rpc_method1(&mut self, input: Input) -> Output;
+    fn rpc_method2(&mut self, input: Input) -> Output;
+    #[retry(max_attempts = 5, initial_backoff_ms = 10, codes(UNAVAILABLE, ABORTED))]
+    fn rpc_method3(&mut self, forward_id: u64, forward_only: bool) -> Result<Output, ()>;
+    fn rpc_method5(&mut self, name: &str, header: &[u8], blob: bincode_grpc::bytes::Bytes) -> usize;
+    fn rpc_method12(&mut self, chunk: Chunk<'_>) -> usize;
+    #[status]
+    #[idempotent]
+    fn rpc_method6(&mut self, fail: bool) -> Result<Output, bincode_grpc::grpcio::RpcStatus>;
+    fn rpc_method7(&mut self, count: u64) -> bincode_grpc::ServerStream<u64>;
+    #[timeout(ms = 5000)]
+    fn rpc_method11(&mut self, info: &bincode_grpc::CallInfo, header: String) -> (String, Option<String>, bool);
+    fn rpc_method16(&mut self, fail: bool) -> Fallible;
+    fn rpc_method17(&mut self, input: Fallible) -> bool;
+}
+
+#[derive(Clone)]
+pub struct TestServer;
+
+#[bincode_grpc::server]
+impl TestService for TestServer {
+    fn rpc_method1(&mut self, input: Input) -> Output {
+        Output {}
+    }
+
+    fn rpc_method2(&mut self, input: Input) -> Output {
+        Output {}
+    }
+
+    fn rpc_method3(&mut self, forward_id: u64, forward_only: bool) -> Result<Output, ()> { Ok(Output {}) }
+
+    fn rpc_method5(&mut self, name: &str, header: &[u8], blob: bincode_grpc::bytes::Bytes) -> usize {
+        name.len() + header.len() + blob.len()
+    }
+
+    fn rpc_method12(&mut self, chunk: Chunk<'_>) -> usize {
+        chunk.name.len() + chunk.data.len()
+    }
+
+    fn rpc_method6(&mut self, fail: bool) -> Result<Output, bincode_grpc::grpcio::RpcStatus> {
+        if fail {
+            Err(bincode_grpc::grpcio::RpcStatus::new(bincode_grpc::grpcio::RpcStatusCode::NOT_FOUND, Some("no output".to_string())))
+        } else {
+            Ok(Output {})
+        }
+    }
+
+    fn rpc_method7(&mut self, count: u64) -> bincode_grpc::ServerStream<u64> {
+        bincode_grpc::ServerStream::iter(0..count)
+    }
+
+    fn rpc_method11(&mut self, info: &bincode_grpc::CallInfo, header: String) -> (String, Option<String>, bool) {
+        let value = info.header(&header).map(|v| String::from_utf8_lossy(v).into_owned());
+        (info.method().to_string(), value, info.remaining().is_some())
+    }
+
+    fn rpc_method16(&mut self, fail: bool) -> Fallible {
+        Fallible { fail }
+    }
+
+    fn rpc_method17(&mut self, input: Fallible) -> bool {
+        input.fail
+    }
+}
+
+/// Synchronous client streaming methods block on the requests on the streaming pool of the
+/// server, apart from its `#[blocking]` methods.
+#[bincode_grpc::service]
+pub trait StreamService {
+    fn rpc_method8(&self, chunks: bincode_grpc::ClientStream<Vec<u8>>) -> u64;
+    fn rpc_method9(&self, values: bincode_grpc::ClientStream<u64>) -> bincode_grpc::ServerStream<u64>;
+    fn rpc_method10(&self, n: u64) -> u64;
+}
+
+pub struct StreamServer;
+
+#[bincode_grpc::server(streaming_pool = 2)]
+impl StreamService for StreamServer {
+    fn rpc_method8(&self, chunks: bincode_grpc::ClientStream<Vec<u8>>) -> u64 {
+        chunks.map(|chunk| chunk.unwrap().len() as u64).sum()
+    }
+
+    fn rpc_method9(&self, values: bincode_grpc::ClientStream<u64>) -> bincode_grpc::ServerStream<u64> {
+        bincode_grpc::ServerStream::new(futures::StreamExt::map(values, |value| value.unwrap() * 2))
+    }
+
+    #[blocking]
+    fn rpc_method10(&self, n: u64) -> u64 {
+        n * 2
+    }
+}
+
+/// Methods taking `&self` share a single server across methods and threads.
+#[bincode_grpc::service]
+pub trait CounterService {
+    fn add(&self, n: u64) -> u64;
+    async fn total(&self) -> u64;
+    async fn add_all(&self, values: bincode_grpc::ClientStream<u64>) -> u64;
+}
+
+#[derive(Default)]
+pub struct Counter {
+    total: std::sync::atomic::AtomicU64,
+}
+
+#[bincode_grpc::server]
+impl CounterService for Counter {
+    #[blocking]
+    fn add(&self, n: u64) -> u64 {
+        self.total.fetch_add(n, std::sync::atomic::Ordering::SeqCst) + n
+    }
+
+    async fn total(&self) -> u64 {
+        self.total.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    async fn add_all(&self, values: bincode_grpc::ClientStream<u64>) -> u64 {
+        let n = futures::StreamExt::fold(values, 0, |sum, value| async move { sum + value.unwrap() }).await;
+        self.add(n)
+    }
+}
+
+/// Denies calls carrying a `x-deny` header, fails every other call carrying a `x-flaky` header
+/// and counts the failed ones.
+#[derive(Default)]
+pub struct DenyInterceptor {
+    failed: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    flaky: std::sync::atomic::AtomicBool,
+}
+
+impl bincode_grpc::Interceptor for DenyInterceptor {
+    fn before(&self, call: &bincode_grpc::interceptor::CallStart) -> Result<(), bincode_grpc::grpcio::RpcStatus> {
+        if call.headers().iter().any(|(k, _)| k == "x-deny") {
+            let details = format!("{} denied", call.method());
+            return Err(bincode_grpc::grpcio::RpcStatus::new(bincode_grpc::grpcio::RpcStatusCode::PERMISSION_DENIED, Some(details)));
+        }
+        if call.headers().iter().any(|(k, _)| k == "x-flaky") && !self.flaky.fetch_xor(true, std::sync::atomic::Ordering::SeqCst) {
+            return Err(bincode_grpc::grpcio::RpcStatus::new(bincode_grpc::grpcio::RpcStatusCode::UNAVAILABLE, None));
+        }
+        Ok(())
+    }
+
+    fn after(&self, call: &bincode_grpc::interceptor::CallEnd) {
+        if call.status().status != bincode_grpc::grpcio::RpcStatusCode::OK {
+            self.failed.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+}
+
+/// Adds a trace id to each call and counts the calls which completed.
+#[derive(Default)]
+pub struct TraceInterceptor {
+    pub completed: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+}
+
+impl bincode_grpc::ClientInterceptor for TraceInterceptor {
+    fn before(&self, _method: &str, opt: bincode_grpc::grpcio::CallOption) -> bincode_grpc::grpcio::Result<bincode_grpc::grpcio::CallOption> {
+        bincode_grpc::interceptor::with_header(opt, "x-trace-id", "trace")
+    }
+
+    fn after(&self, _call: &bincode_grpc::interceptor::CallEnd) {
+        self.completed.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+/// Builds a server with the services of the example bound to `host:port`, port 0 binds a free
+/// port which `Server::bind_addrs` returns. The counter counts the calls failed on the server.
+pub fn build_server(host: &str, port: u16) -> (bincode_grpc::grpcio::Server, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+    let interceptor = DenyInterceptor::default();
+    let failed = interceptor.failed.clone();
+    let service = create_test_service_with_options(
+        TestServer {},
+        bincode_grpc::ServiceOptions::new().method_decode_limit("rpc_method3", 16).interceptor(interceptor),
+    );
+    let env = std::sync::Arc::new(bincode_grpc::grpcio::Environment::new(8));
+    let channel_builder = bincode_grpc::grpcio::ChannelBuilder::new(env.clone());
+    let server = bincode_grpc::grpcio::ServerBuilder::new(env)
+        .register_service(service)
+        .register_service(create_test_service3(std::sync::Arc::new(TestServer {})))
+        .register_service(create_stream_service(std::sync::Arc::new(StreamServer)))
+        .register_service(create_counter_service(std::sync::Arc::new(Counter::default())))
+        .register_service(create_test_service2(TestServer2))
+        .bind(host, port)
+        .channel_args(channel_builder.build_args())
+        .build()
+        .unwrap();
+    (server, failed)
+}
+
+// The following are for other testing purposes.
+
+/// JSON messages, handy for debugging endpoints.
+pub enum JsonCodec {}
+
+impl bincode_grpc::Codec for JsonCodec {
+    fn encode<M: Serialize>(msg: &M, buf: &mut Vec<u8>) -> Result<(), bincode_grpc::bi_codec::CodecError> {
+        serde_json::to_writer(buf, msg).map_err(|e| bincode_grpc::bi_codec::CodecError::Invalid(Box::new(e)))
+    }
+
+    fn decode<M: serde::de::DeserializeOwned>(buf: &[u8], _limit: Option<u64>) -> Result<M, bincode_grpc::bi_codec::CodecError> {
+        serde_json::from_slice(buf).map_err(|e| bincode_grpc::bi_codec::CodecError::Invalid(Box::new(e)))
+    }
+}
+
+#[bincode_grpc::service(codec = JsonCodec, decode_limit = 1048576)]
+pub trait TestService3 {
+    #[decode_limit(1024)]
+    fn rpc_method4(&self) -> Output;
+}
+
+#[bincode_grpc::service(codec(varint, big_endian, reject_trailing))]
+pub trait TestService2 {
+    #[compress(zstd, min_size = 4096)]
+    fn rpc_method1(&mut self, input: Input) -> Output;
+    fn rpc_method2(&mut self, input: Input) -> Output;
+    #[compress(zstd, min_size = 4096)]
+    #[decode_limit(65536)]
+    fn rpc_method13(&mut self, data: Vec<u8>) -> u64;
+    #[compress(lz4)]
+    fn rpc_method14(&mut self, data: Vec<u8>) -> Vec<u8>;
+    fn rpc_method15(&mut self, n: u64, data: Vec<u8>) -> u64;
+}
+
+#[derive(Clone)]
+pub struct TestServer2;
+
+#[bincode_grpc::server]
+impl TestService2 for TestServer2 {
+    fn rpc_method1(&mut self, _input: Input) -> Output {
+        Output {}
+    }
+
+    fn rpc_method2(&mut self, _input: Input) -> Output {
+        Output {}
+    }
+
+    fn rpc_method13(&mut self, data: Vec<u8>) -> u64 {
+        data.len() as u64
+    }
+
+    fn rpc_method14(&mut self, data: Vec<u8>) -> Vec<u8> {
+        data
+    }
+
+    fn rpc_method15(&mut self, n: u64, data: Vec<u8>) -> u64 {
+        n + data.len() as u64
+    }
+}
+
+#[bincode_grpc::server(blocking_pool = 2)]
+impl TestService3 for TestServer {
+    fn rpc_method4(&self) -> Output {
+        Output {}
+    }
+}
+
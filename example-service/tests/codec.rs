@@ -0,0 +1,43 @@
+mod common;
+
+use bincode_grpc::bincode::{DefaultOptions, Options};
+use bincode_grpc::bi_codec::raw_request;
+use bincode_grpc::grpcio::{CallOption, RpcStatusCode};
+use example_service::{TestService2Client, TEST_SERVICE2_METHOD_RPC_METHOD15};
+
+/// the options of `TestService2`
+fn options() -> impl Options {
+    DefaultOptions::new().with_varint_encoding().with_big_endian().reject_trailing_bytes()
+}
+
+#[test]
+fn configured_codec() {
+    let env = common::start();
+    let client = TestService2Client::new(env.channel.clone());
+    assert_eq!(client.rpc_method15(300, vec![1, 2, 3]).unwrap(), 303);
+    // requests on the wire are varint and big endian
+    let payload = options().serialize(&(300u64, vec![1u8, 2, 3])).unwrap();
+    assert_eq!(payload, [0xfb, 0x01, 0x2c, 0x03, 1, 2, 3]);
+    let method = raw_request(&TEST_SERVICE2_METHOD_RPC_METHOD15);
+    assert_eq!(client.unary_call(&method, &payload, CallOption::default()).unwrap(), 303);
+}
+
+#[test]
+fn trailing_bytes() {
+    let env = common::start();
+    let client = TestService2Client::new(env.channel.clone());
+    let mut payload = options().serialize(&(300u64, vec![1u8, 2, 3])).unwrap();
+    payload.push(0);
+    let method = raw_request(&TEST_SERVICE2_METHOD_RPC_METHOD15);
+    common::assert_status(client.unary_call(&method, &payload, CallOption::default()), RpcStatusCode::INVALID_ARGUMENT);
+}
+
+#[test]
+fn fixint_clients() {
+    let env = common::start();
+    let client = TestService2Client::new(env.channel.clone());
+    // the default fixint little endian encoding is read as a shorter varint request and trailing bytes
+    let payload = bincode_grpc::bincode::serialize(&(300u64, vec![1u8, 2, 3])).unwrap();
+    let method = raw_request(&TEST_SERVICE2_METHOD_RPC_METHOD15);
+    common::assert_status(client.unary_call(&method, &payload, CallOption::default()), RpcStatusCode::INVALID_ARGUMENT);
+}
@@ -0,0 +1,30 @@
+//! Helpers shared by the integration tests, each test crate uses a part of them.
+#![allow(dead_code)]
+
+use bincode_grpc::grpcio::{Channel, ChannelBuilder, Environment, Error, Result, RpcStatusCode, Server};
+use std::sync::atomic::AtomicUsize;
+use std::sync::Arc;
+
+/// A server of the example running on a free port of the loopback interface.
+pub struct TestEnv {
+    pub channel: Channel,
+    /// the calls failed on the server
+    pub failed: Arc<AtomicUsize>,
+    _server: Server,
+}
+
+pub fn start() -> TestEnv {
+    let (mut server, failed) = example_service::build_server("127.0.0.1", 0);
+    server.start();
+    let (host, port) = server.bind_addrs().next().expect("the server is not bound");
+    let env = Arc::new(Environment::new(2));
+    let channel = ChannelBuilder::new(env).connect(&format!("{}:{}", host, port));
+    TestEnv { channel, failed, _server: server }
+}
+
+pub fn assert_status<T: std::fmt::Debug>(result: Result<T>, code: RpcStatusCode) {
+    match result {
+        Err(Error::RpcFailure(status)) => assert_eq!(status.status, code, "{:?}", status.details),
+        other => panic!("expected {:?}, got {:?}", code, other),
+    }
+}
@@ -0,0 +1,23 @@
+mod common;
+
+use bincode_grpc::grpcio::RpcStatusCode;
+use example_service::{Input, TestService2Client};
+
+#[test]
+fn compressed_calls() {
+    let env = common::start();
+    let client = TestService2Client::new(env.channel.clone());
+    assert!(client.rpc_method1(Input {}).is_ok());
+    assert_eq!(client.rpc_method13(vec![7; 16 << 10]).unwrap(), 16 << 10);
+    let data: Vec<u8> = (0..1 << 16).map(|i| (i % 251) as u8).collect();
+    assert_eq!(client.rpc_method14(&data).unwrap(), data);
+}
+
+#[test]
+fn compressed_decode_limit() {
+    let env = common::start();
+    let client = TestService2Client::new(env.channel.clone());
+    // the request compresses well below the decode limit of `rpc_method13` but not once inflated
+    common::assert_status(client.rpc_method13(vec![0; 1 << 20]), RpcStatusCode::RESOURCE_EXHAUSTED);
+    assert_eq!(client.rpc_method13(vec![0; 1 << 10]).unwrap(), 1 << 10);
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use bincode_grpc::grpcio::{CallOption, MetadataBuilder, RpcStatusCode};
+use example_service::{Input, TestServiceClient, TraceInterceptor};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+#[test]
+fn server_interceptors() {
+    let env = common::start();
+    let client = TestServiceClient::new(env.channel.clone());
+    let mut headers = MetadataBuilder::new();
+    headers.add_str("x-deny", "1").unwrap();
+    let opt = CallOption::default().headers(headers.build());
+    common::assert_status(client.rpc_method1_opt(Input {}, opt), RpcStatusCode::PERMISSION_DENIED);
+    assert!(client.rpc_method6(true).unwrap().is_err());
+    // denied calls don't reach `after`
+    assert_eq!(env.failed.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn client_interceptors() {
+    let env = common::start();
+    let tracer = TraceInterceptor::default();
+    let completed = tracer.completed.clone();
+    let client = TestServiceClient::new(env.channel.clone()).with_interceptor(tracer);
+    let (_, trace_id, _) = client.rpc_method11("x-trace-id".to_string()).unwrap();
+    assert_eq!(trace_id.as_deref(), Some("trace"));
+    let items = futures::executor::block_on_stream(client.rpc_method7(2).unwrap());
+    assert_eq!(items.count(), 2);
+    assert_eq!(completed.load(Ordering::SeqCst), 2);
+    // dropping an asynchronous call cancels it, which ends it for the interceptors
+    drop(client.rpc_method1_async(Input {}));
+    assert_eq!(completed.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn retries() {
+    let env = common::start();
+    // `rpc_method1` is retried after the first flaky call fails, `rpc_method2` isn't
+    let flaky = || bincode_grpc::interceptor::with_header(CallOption::default(), "x-flaky", "1").unwrap();
+    let client = TestServiceClient::new(env.channel.clone())
+        .with_retry_policy(bincode_grpc::RetryPolicy::new().initial_backoff(Duration::from_millis(1)));
+    assert!(client.rpc_method1_opt(Input {}, flaky()).is_ok());
+    common::assert_status(client.rpc_method2_opt(&Input {}, flaky()), RpcStatusCode::UNAVAILABLE);
+}
+
+#[test]
+fn status_retries() {
+    let env = common::start();
+    let flaky = || bincode_grpc::interceptor::with_header(CallOption::default(), "x-flaky", "1").unwrap();
+    // `UNAVAILABLE` is not an error of `rpc_method6`, so the retrying client retries it
+    let client = TestServiceClient::new(env.channel.clone())
+        .with_retry_policy(bincode_grpc::RetryPolicy::new().initial_backoff(Duration::from_millis(1)));
+    assert!(client.rpc_method6_opt(false, flaky()).unwrap().is_ok());
+    // and clients which don't retry fail with it instead of returning it as the error of the method
+    let client = TestServiceClient::new(env.channel.clone()).with_retry_policy(bincode_grpc::RetryPolicy::never());
+    common::assert_status(client.rpc_method6_opt(false, flaky()), RpcStatusCode::UNAVAILABLE);
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use bincode_grpc::bi_codec::{self, Bincode};
+use bincode_grpc::grpcio::{CallOption, Marshaller, Method, Result, RpcStatusCode};
+use example_service::{Input, TestServiceClient, TEST_SERVICE_METHOD_RPC_METHOD1, TEST_SERVICE_METHOD_RPC_METHOD3};
+use example_service::{TEST_SERVICE_METHOD_RPC_METHOD5, TEST_SERVICE_METHOD_RPC_METHOD7};
+
+/// Sends `payload` as-is to the server side of `method`, bypassing the request marshaller.
+fn call_raw<Req, Resp>(client: &TestServiceClient, method: &Method<Req, Resp>, payload: Vec<u8>) -> Result<Resp> {
+    client.unary_call(&bi_codec::raw_request(method), &payload, CallOption::default())
+}
+
+#[test]
+fn malformed_requests() {
+    let env = common::start();
+    let client = TestServiceClient::new(env.channel.clone());
+
+    // truncated request: `(u64, bool)` needs 9 bytes
+    let truncated = call_raw(&client, &TEST_SERVICE_METHOD_RPC_METHOD3, vec![1, 0, 0, 0]);
+    common::assert_status(truncated, RpcStatusCode::INVALID_ARGUMENT);
+
+    // garbage request: 7 is not a valid encoding of `bool`
+    let garbage = call_raw(&client, &TEST_SERVICE_METHOD_RPC_METHOD3, vec![1, 0, 0, 0, 0, 0, 0, 0, 7]);
+    common::assert_status(garbage, RpcStatusCode::INVALID_ARGUMENT);
+
+    // the server survives malformed requests
+    assert!(client.rpc_method3(1, true).is_ok());
+}
+
+#[test]
+fn malformed_borrowed_requests() {
+    let env = common::start();
+    let client = TestServiceClient::new(env.channel.clone());
+    // `name: &str` is not valid UTF-8, followed by an empty `header` and `blob`
+    let mut payload = vec![2, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xfe];
+    payload.extend_from_slice(&[0; 16]);
+    let invalid_utf8 = call_raw(&client, &TEST_SERVICE_METHOD_RPC_METHOD5, payload);
+    common::assert_status(invalid_utf8, RpcStatusCode::INVALID_ARGUMENT);
+}
+
+#[test]
+fn malformed_streaming_requests() {
+    let env = common::start();
+    let client = TestServiceClient::new(env.channel.clone());
+    // truncated request of a server streaming method: `count: u64` needs 8 bytes
+    let method = bi_codec::raw_request(&TEST_SERVICE_METHOD_RPC_METHOD7);
+    let items = client.server_streaming(&method, &vec![3, 0, 0], CallOption::default()).unwrap();
+    let items = futures::executor::block_on_stream(items).collect::<Result<Vec<_>>>();
+    common::assert_status(items, RpcStatusCode::INVALID_ARGUMENT);
+}
+
+#[test]
+fn oversized_requests() {
+    let env = common::start();
+    let client = TestServiceClient::new(env.channel.clone());
+    // the decode limit of `rpc_method3` is 16 bytes
+    let oversized = call_raw(&client, &TEST_SERVICE_METHOD_RPC_METHOD3, vec![0; 64]);
+    common::assert_status(oversized, RpcStatusCode::RESOURCE_EXHAUSTED);
+}
+
+#[test]
+fn malformed_responses() {
+    let env = common::start();
+    let client = TestServiceClient::new(env.channel.clone());
+    // `Output` is empty on the wire, so decoding it as `u64` must fail on the client
+    let mismatched_response: Method<(Input,), u64> = Method {
+        ty: TEST_SERVICE_METHOD_RPC_METHOD1.ty,
+        name: TEST_SERVICE_METHOD_RPC_METHOD1.name,
+        req_mar: Marshaller { ser: bi_codec::ser::<_, Bincode>, de: bi_codec::de::<_, Bincode> },
+        resp_mar: Marshaller { ser: bi_codec::ser::<_, Bincode>, de: bi_codec::de::<_, Bincode> },
+    };
+    let response = client.unary_call(&mismatched_response, &(Input {},), CallOption::default());
+    common::assert_status(response, RpcStatusCode::INVALID_ARGUMENT);
+}
+
+#[test]
+fn mismatched_fingerprints() {
+    let env = common::start();
+    let client = TestServiceClient::new(env.channel.clone());
+    let opt = bincode_grpc::schema::with_fingerprint(CallOption::default(), 0).unwrap();
+    let mismatched_types = client.unary_call(&TEST_SERVICE_METHOD_RPC_METHOD1, &(Input {},), opt);
+    common::assert_status(mismatched_types, RpcStatusCode::FAILED_PRECONDITION);
+}
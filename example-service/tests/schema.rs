@@ -0,0 +1,34 @@
+//! The types are only described, never built.
+#![allow(dead_code)]
+
+use bincode_grpc::schema::fingerprint;
+
+#[derive(bincode_grpc::Schema)]
+pub struct Node {
+    children: Vec<Node>,
+}
+
+#[derive(bincode_grpc::Schema)]
+pub struct Leaf {
+    children: Vec<String>,
+}
+
+#[derive(bincode_grpc::Schema)]
+pub enum Expr {
+    Value(u64),
+    Add(Box<Expr>, Box<Expr>),
+    Call { name: String, args: Vec<Expr> },
+}
+
+#[derive(bincode_grpc::Schema)]
+pub struct Tree<T> {
+    value: T,
+    children: Vec<Tree<T>>,
+}
+
+#[test]
+fn recursive_types() {
+    assert_ne!(fingerprint::<(Node,), Node>(), fingerprint::<(Leaf,), Leaf>());
+    assert_eq!(fingerprint::<(Expr,), Expr>(), fingerprint::<(Expr,), Expr>());
+    assert_ne!(fingerprint::<(Tree<u32>,), ()>(), fingerprint::<(Tree<u64>,), ()>());
+}
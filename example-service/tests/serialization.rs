@@ -0,0 +1,29 @@
+mod common;
+
+use bincode_grpc::grpcio::{ChannelBuilder, Environment, Error, RpcStatusCode};
+use example_service::{Fallible, TestServiceClient};
+use std::sync::Arc;
+
+#[test]
+fn unserializable_responses() {
+    let env = common::start();
+    let client = TestServiceClient::new(env.channel.clone());
+    common::assert_status(client.rpc_method16(true), RpcStatusCode::INTERNAL);
+    // the server keeps serving
+    assert!(!client.rpc_method16(false).unwrap().fail);
+    common::assert_status(client.rpc_method16(true), RpcStatusCode::INTERNAL);
+    assert!(client.rpc_method1(example_service::Input {}).is_ok());
+}
+
+#[test]
+fn unserializable_requests() {
+    let env = common::start();
+    let client = TestServiceClient::new(env.channel.clone());
+    assert!(matches!(client.rpc_method17(Fallible { fail: true }), Err(Error::Codec(_))));
+    assert!(!client.rpc_method17(Fallible { fail: false }).unwrap());
+    // nothing is sent: a client without a server fails the same way instead of being unavailable
+    let channel = ChannelBuilder::new(Arc::new(Environment::new(1))).connect("127.0.0.1:1");
+    let client = TestServiceClient::new(channel);
+    assert!(matches!(client.rpc_method17(Fallible { fail: true }), Err(Error::Codec(_))));
+    assert!(matches!(futures::executor::block_on(client.rpc_method17_async(Fallible { fail: true })), Err(Error::Codec(_))));
+}
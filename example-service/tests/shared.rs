@@ -0,0 +1,30 @@
+mod common;
+
+use example_service::{CounterServiceClient, TestService3Client};
+use std::time::Duration;
+
+#[test]
+fn shared_server() {
+    let env = common::start();
+    let client = CounterServiceClient::new(env.channel.clone()).with_timeout(Duration::from_secs(5));
+    assert_eq!(client.add(2).unwrap(), 2);
+    assert_eq!(client.add(3).unwrap(), 5);
+    assert_eq!(client.total().unwrap(), 5);
+}
+
+#[test]
+fn async_calls() {
+    let env = common::start();
+    let client = CounterServiceClient::new(env.channel.clone());
+    // asynchronous calls are awaited concurrently on an executor
+    let (a, b) = futures::executor::block_on(async { futures::join!(client.add_async(1), client.add_async(1)) });
+    assert_eq!(a.unwrap() + b.unwrap(), 1 + 2);
+    assert_eq!(futures::executor::block_on(client.total_async()).unwrap(), 2);
+}
+
+#[test]
+fn blocking_pool() {
+    let env = common::start();
+    let client = TestService3Client::new(env.channel.clone());
+    assert!(client.rpc_method4().is_ok());
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use example_service::{CounterServiceClient, StreamServiceClient, TestServiceClient};
+
+#[test]
+fn server_streaming() {
+    let env = common::start();
+    let client = TestServiceClient::new(env.channel.clone());
+    let items = futures::executor::block_on_stream(client.rpc_method7(3).unwrap());
+    assert_eq!(items.collect::<Result<Vec<_>, _>>().unwrap(), vec![0, 1, 2]);
+}
+
+#[test]
+fn client_streaming() {
+    let env = common::start();
+    let client = StreamServiceClient::new(env.channel.clone());
+    let (mut sender, receiver) = client.rpc_method8().unwrap();
+    futures::executor::block_on(async {
+        for chunk in [vec![0; 3], vec![0; 4]] {
+            futures::SinkExt::send(&mut sender, chunk).await.unwrap();
+        }
+        futures::SinkExt::close(&mut sender).await.unwrap();
+    });
+    assert_eq!(futures::executor::block_on(receiver).unwrap(), 7);
+}
+
+#[test]
+fn async_client_streaming() {
+    let env = common::start();
+    let client = CounterServiceClient::new(env.channel.clone());
+    let (mut sender, receiver) = client.add_all().unwrap();
+    futures::executor::block_on(async {
+        for value in 1..=4 {
+            futures::SinkExt::send(&mut sender, value).await.unwrap();
+        }
+        futures::SinkExt::close(&mut sender).await.unwrap();
+    });
+    assert_eq!(futures::executor::block_on(receiver).unwrap(), 10);
+}
+
+#[test]
+fn duplex_streaming() {
+    let env = common::start();
+    let client = StreamServiceClient::new(env.channel.clone());
+    let (mut sender, receiver) = client.rpc_method9().unwrap();
+    futures::executor::block_on(async {
+        for value in 1..=3 {
+            futures::SinkExt::send(&mut sender, value).await.unwrap();
+        }
+        futures::SinkExt::close(&mut sender).await.unwrap();
+    });
+    let doubled = futures::executor::block_on_stream(receiver).collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(doubled, vec![2, 4, 6]);
+}
+
+#[test]
+fn idle_client_streams() {
+    // uploads holding every thread of the streaming pool leave the blocking methods running
+    let env = common::start();
+    let client = StreamServiceClient::new(env.channel.clone());
+    let calls: Vec<_> = (0..4).map(|_| client.rpc_method8().unwrap()).collect();
+    for n in 0..8 {
+        assert_eq!(client.rpc_method10(n).unwrap(), n * 2);
+    }
+    for (mut sender, receiver) in calls {
+        futures::executor::block_on(futures::SinkExt::close(&mut sender)).unwrap();
+        assert_eq!(futures::executor::block_on(receiver).unwrap(), 0);
+    }
+}
+
+#[test]
+fn concurrent_client_streams() {
+    // more uploads than threads in the streaming pool complete once earlier ones end
+    let env = common::start();
+    let client = StreamServiceClient::new(env.channel.clone());
+    let calls: Vec<_> = (0..32).map(|_| client.rpc_method8().unwrap()).collect();
+    let receivers: Vec<_> = calls
+        .into_iter()
+        .map(|(mut sender, receiver)| {
+            futures::executor::block_on(async {
+                futures::SinkExt::send(&mut sender, vec![0; 5]).await.unwrap();
+                futures::SinkExt::close(&mut sender).await.unwrap();
+            });
+            receiver
+        })
+        .collect();
+    for receiver in receivers {
+        assert_eq!(futures::executor::block_on(receiver).unwrap(), 5);
+    }
+}
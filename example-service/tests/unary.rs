@@ -0,0 +1,84 @@
+mod common;
+
+use bincode_grpc::grpcio::{CallOption, MetadataBuilder, RpcStatusCode};
+use example_service::{Chunk, Input, TestServiceClient, TEST_SERVICE_METHOD_RPC_METHOD11, TEST_SERVICE_METHOD_RPC_METHOD3};
+use std::time::Duration;
+
+#[test]
+fn unary_call() {
+    let env = common::start();
+    let client = TestServiceClient::new(env.channel.clone());
+    assert!(client.rpc_method1(Input {}).is_ok());
+    assert!(client.rpc_method3(1, true).unwrap().is_ok());
+}
+
+#[test]
+fn borrowed_arguments() {
+    let env = common::start();
+    let client = TestServiceClient::new(env.channel.clone());
+    let blob = vec![7; 1 << 20];
+    assert_eq!(client.rpc_method5("blob", vec![1, 2, 3], &blob).unwrap(), 4 + 3 + (1 << 20));
+}
+
+#[test]
+fn borrowed_struct_args() {
+    let env = common::start();
+    let client = TestServiceClient::new(env.channel.clone());
+    let data = vec![7; 1024];
+    let chunk = Chunk { name: "chunk", data: &data };
+    assert_eq!(client.rpc_method12(&chunk).unwrap(), 5 + 1024);
+    assert_eq!(client.rpc_method12(Chunk { name: "", data: &data[..2] }).unwrap(), 2);
+}
+
+#[test]
+fn status_methods() {
+    let env = common::start();
+    let client = TestServiceClient::new(env.channel.clone());
+    assert!(client.rpc_method6(false).unwrap().is_ok());
+    let not_found = client.rpc_method6(true).unwrap().unwrap_err();
+    assert_eq!(not_found.status, RpcStatusCode::NOT_FOUND);
+    let not_found = futures::executor::block_on(client.rpc_method6_async(true)).unwrap();
+    assert_eq!(not_found.unwrap_err().status, RpcStatusCode::NOT_FOUND);
+}
+
+#[test]
+fn call_info() {
+    let env = common::start();
+    let client = TestServiceClient::new(env.channel.clone());
+    let mut headers = MetadataBuilder::new();
+    headers.add_str("x-request-id", "42").unwrap();
+    let opt = CallOption::default().headers(headers.build()).timeout(Duration::from_secs(10));
+    let (method, value, has_deadline) = client.rpc_method11_opt("x-request-id".to_string(), opt).unwrap();
+    assert_eq!((method.as_str(), value.as_deref(), has_deadline), ("/example.TestService/RpcMethod11", Some("42"), true));
+}
+
+#[test]
+fn advisory_deadline() {
+    let env = common::start();
+    let client = TestServiceClient::new(env.channel.clone());
+    // clients other than the generated ones do not send the advisory timeout header
+    let opt = CallOption::default().timeout(Duration::from_secs(10));
+    let response = client.unary_call(&TEST_SERVICE_METHOD_RPC_METHOD11, &("x-request-id".to_string(),), opt).unwrap();
+    assert!(!response.2);
+}
+
+#[test]
+fn default_timeouts() {
+    let env = common::start();
+    // the default timeout of `rpc_method11` is declared in the trait
+    let client = TestServiceClient::new(env.channel.clone());
+    assert!(client.rpc_method11("x-request-id".to_string()).unwrap().2);
+    // the one of the client applies to the other methods
+    let client = TestServiceClient::new(env.channel.clone()).with_timeout(Duration::from_nanos(1));
+    common::assert_status(client.rpc_method1(Input {}), RpcStatusCode::DEADLINE_EXCEEDED);
+}
+
+#[test]
+fn legacy_names() {
+    let env = common::start();
+    let client = TestServiceClient::new(env.channel.clone());
+    // clients predating method paths still reach `rpc_method3` under its legacy name
+    let legacy = bincode_grpc::grpcio::Method { name: "TEST_SERVICE_METHOD_RPC_METHOD3", ..TEST_SERVICE_METHOD_RPC_METHOD3 };
+    let response = client.unary_call(&legacy, &(1u64, true), CallOption::default());
+    assert!(response.unwrap().is_ok());
+}